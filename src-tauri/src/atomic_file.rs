@@ -0,0 +1,47 @@
+use std::{
+    fs::{rename, File},
+    io::{self, Write},
+    path::Path,
+};
+
+// 一時ファイルに書き出して同期してから置き換えることで書き込み途中の状態が残らないようにする
+pub(crate) fn write_atomically<C: AsRef<[u8]>>(path: &Path, content: C) -> io::Result<()> {
+    let mut temporary_file_name = path.file_name().unwrap_or_default().to_owned();
+    temporary_file_name.push(".tmp");
+    let temporary_path = path.with_file_name(temporary_file_name);
+
+    let mut f = File::create(&temporary_path)?;
+    f.write_all(content.as_ref())?;
+    f.sync_all()?;
+
+    rename(&temporary_path, path)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::{create_dir_all, read_dir, read_to_string, remove_dir_all};
+
+    use super::*;
+
+    #[test]
+    fn write_atomically_1() {
+        let dir = std::env::temp_dir().join(format!(
+            "tconcierge_write_atomically_1_{}",
+            std::process::id()
+        ));
+        create_dir_all(&dir).unwrap();
+        let path = dir.join("history.jsonl");
+
+        write_atomically(&path, "old").unwrap();
+        write_atomically(&path, "new").unwrap();
+
+        let content = read_to_string(&path).unwrap();
+        let file_count = read_dir(&dir).unwrap().count();
+
+        remove_dir_all(&dir).unwrap();
+
+        // 一時ファイルは残らない
+        assert_eq!(content, "new");
+        assert_eq!(file_count, 1);
+    }
+}
@@ -4,20 +4,30 @@ use typing_engine::{
     DisplayInfo,
 };
 
+use crate::ghost::GhostPosition;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct DisplayInformation {
     view: ViewDisplayInformation,
     key_stroke: KeyStrokeDisplayInformation,
+    // ゴーストと競争している場合の同じ経過時間でのゴーストの位置
+    ghost: Option<GhostPosition>,
 }
 
-impl From<DisplayInfo> for DisplayInformation {
-    fn from(di: DisplayInfo) -> Self {
+impl DisplayInformation {
+    // ラップの時間はタイピングエンジンからは綴りで区切った場合に取得できないので別に記録したものを使う
+    pub(crate) fn new(di: &DisplayInfo, lap_end_time: Vec<f64>) -> Self {
         Self {
             view: di.view_info().into(),
-            key_stroke: di.key_stroke_info().into(),
+            key_stroke: KeyStrokeDisplayInformation::new(di.key_stroke_info(), lap_end_time),
+            ghost: None,
         }
     }
+
+    pub(crate) fn set_ghost(&mut self, ghost: Option<GhostPosition>) {
+        self.ghost = ghost;
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,8 +61,8 @@ pub(crate) struct KeyStrokeDisplayInformation {
     lap_end_time: Vec<f64>,
 }
 
-impl From<&KeyStrokeDisplayInfo> for KeyStrokeDisplayInformation {
-    fn from(ksdi: &KeyStrokeDisplayInfo) -> Self {
+impl KeyStrokeDisplayInformation {
+    fn new(ksdi: &KeyStrokeDisplayInfo, lap_end_time: Vec<f64>) -> Self {
         let on_typing_statistics = ksdi.on_typing_statistics();
         let on_typing_statistics_ideal = ksdi.on_typing_statistics_ideal();
 
@@ -63,12 +73,17 @@ impl From<&KeyStrokeDisplayInfo> for KeyStrokeDisplayInformation {
             progress: on_typing_statistics_ideal.finished_count() as f64
                 / on_typing_statistics_ideal.whole_count() as f64,
             lap_end_positions: on_typing_statistics.lap_end_positions().clone(),
-            lap_end_time: on_typing_statistics_ideal
-                .lap_end_time()
-                .unwrap()
-                .iter()
-                .map(|d| d.as_millis() as f64)
-                .collect(),
+            lap_end_time,
         }
     }
 }
+
+// 打ち終えたラップの数
+// ラップ末の位置はどの単位で区切った場合も打鍵列上の位置に変換されている
+pub(crate) fn finished_lap_count(ksdi: &KeyStrokeDisplayInfo) -> usize {
+    ksdi.on_typing_statistics()
+        .lap_end_positions()
+        .iter()
+        .filter(|lap_end_position| **lap_end_position < ksdi.current_cursor_position())
+        .count()
+}
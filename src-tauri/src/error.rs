@@ -0,0 +1,112 @@
+use std::io;
+
+use serde::{Deserialize, Serialize};
+use typing_engine::{KeyStrokeCharError, TypingEngineError};
+
+use crate::game_session::GameSessionError;
+use crate::library::LibraryError;
+use crate::query_request::QueryRequestError;
+use crate::replay::ReplayError;
+
+// コマンドが失敗した時にUI側に返すエラー
+// UI側では種類で処理を分岐しメッセージは表示やログに使う
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ToUIError {
+    kind: ToUIErrorKind,
+    message: String,
+}
+
+impl ToUIError {
+    pub(crate) fn new(kind: ToUIErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ToUIErrorKind {
+    // コマンドを呼び出す順番が不正（クエリの確定前に開始した、終了後に打鍵したなど）
+    InvalidGameState,
+    // 指定された辞書が存在しない
+    DictionaryNotFound,
+    // クエリの要求が不正
+    InvalidQueryRequest,
+    // 打鍵として扱えないキーが渡された
+    InvalidKeyInput,
+    // リプレイを再生できない
+    InvalidReplay,
+    // 取り込もうとした辞書ファイルが不正
+    InvalidDictionary,
+    // 辞書の作成・編集の要求が不正
+    InvalidDictionaryEdit,
+    // ファイルの読み書きに失敗した
+    Io,
+    // UI側へのイベントの通知に失敗した
+    EventEmission,
+}
+
+impl From<TypingEngineError> for ToUIError {
+    fn from(e: TypingEngineError) -> Self {
+        // タイピングエンジンのエラーは全て呼び出す順番の誤りによるもの
+        Self::new(ToUIErrorKind::InvalidGameState, e.to_string())
+    }
+}
+
+impl From<KeyStrokeCharError> for ToUIError {
+    fn from(e: KeyStrokeCharError) -> Self {
+        Self::new(ToUIErrorKind::InvalidKeyInput, e.to_string())
+    }
+}
+
+impl From<io::Error> for ToUIError {
+    fn from(e: io::Error) -> Self {
+        Self::new(ToUIErrorKind::Io, e.to_string())
+    }
+}
+
+impl From<tauri::Error> for ToUIError {
+    fn from(e: tauri::Error) -> Self {
+        Self::new(ToUIErrorKind::EventEmission, e.to_string())
+    }
+}
+
+impl From<QueryRequestError> for ToUIError {
+    fn from(e: QueryRequestError) -> Self {
+        Self::new(ToUIErrorKind::InvalidQueryRequest, e.to_string())
+    }
+}
+
+impl From<LibraryError> for ToUIError {
+    fn from(e: LibraryError) -> Self {
+        let kind = match e {
+            LibraryError::DictionaryNotFound(..) => ToUIErrorKind::DictionaryNotFound,
+            LibraryError::InvalidDictionaryFile(..) | LibraryError::NoValidVocabulary(..) => {
+                ToUIErrorKind::InvalidDictionary
+            }
+            LibraryError::Io(..) => ToUIErrorKind::Io,
+            LibraryError::ReadOnlyDictionary(..)
+            | LibraryError::InvalidDictionaryName(..)
+            | LibraryError::DictionaryAlreadyExists(..)
+            | LibraryError::EntryNotFound(..)
+            | LibraryError::InvalidEntry(..) => ToUIErrorKind::InvalidDictionaryEdit,
+        };
+
+        Self::new(kind, e.to_string())
+    }
+}
+
+impl From<GameSessionError> for ToUIError {
+    fn from(e: GameSessionError) -> Self {
+        Self::new(ToUIErrorKind::InvalidGameState, e.to_string())
+    }
+}
+
+impl From<ReplayError> for ToUIError {
+    fn from(e: ReplayError) -> Self {
+        Self::new(ToUIErrorKind::InvalidReplay, e.to_string())
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::history::HistoryEntry;
+use crate::library::{DictionaryOrigin, DictionaryType};
+use crate::query_request::VocabularyQuantifierFromUI;
+use crate::result::TypingResult;
+use crate::scoring::ScoringFormula;
+
+// CSVの列の並び
+// 列の構成はREADMEに記載しているので変える場合には末尾に追加する
+const CSV_HEADER: [&str; 14] = [
+    "id",
+    "finishedAtMs",
+    "dictionaryType",
+    "dictionaries",
+    "quantifierType",
+    "quantifierValue",
+    "totalTimeMs",
+    "keyStrokeCount",
+    "idealKeyStrokeCount",
+    "missCount",
+    "cpm",
+    "accuracy",
+    "scoringFormula",
+    "score",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ExportFormat {
+    Csv,
+    Json,
+}
+
+// 書き出す1回分のタイピングの結果
+// JSONではこの構造体の配列として書き出す
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExportRow {
+    // 履歴から書き出す場合のみ存在する
+    id: Option<u64>,
+    finished_at_ms: Option<u64>,
+    dictionary_type: DictionaryType,
+    // 「builtin/辞書名」か「user_defined/辞書名」の形式
+    dictionaries: Vec<String>,
+    // 終了条件を記録し始める前の履歴では存在しないこともある
+    quantifier_type: Option<String>,
+    quantifier_value: Option<usize>,
+    total_time_ms: usize,
+    // 実際に打ったローマ字系列の字数
+    key_stroke_count: usize,
+    ideal_key_stroke_count: usize,
+    miss_count: usize,
+    cpm: usize,
+    // 百分率
+    accuracy: f64,
+    scoring_formula: ScoringFormula,
+    score: f64,
+}
+
+impl ExportRow {
+    pub(crate) fn new(
+        dictionary_type: &DictionaryType,
+        used_dictionaries: &[(DictionaryOrigin, String)],
+        quantifier: Option<&VocabularyQuantifierFromUI>,
+        result: &TypingResult,
+    ) -> Self {
+        let (quantifier_type, quantifier_value) = match quantifier {
+            Some(VocabularyQuantifierFromUI::KeyStroke { count }) => {
+                (Some("keyStroke"), Some(count.get()))
+            }
+            Some(VocabularyQuantifierFromUI::Vocabulary { count }) => {
+                (Some("vocabulary"), Some(count.get()))
+            }
+            Some(VocabularyQuantifierFromUI::TimeLimit { seconds }) => {
+                (Some("timeLimit"), Some(seconds.get()))
+            }
+            None => (None, None),
+        };
+
+        let key_stroke = result.key_stroke();
+        let scores = result.scores().key_stroke();
+
+        Self {
+            id: None,
+            finished_at_ms: None,
+            dictionary_type: dictionary_type.clone(),
+            dictionaries: used_dictionaries
+                .iter()
+                .map(|(origin, name)| {
+                    let origin = match origin {
+                        DictionaryOrigin::Builtin => "builtin",
+                        DictionaryOrigin::UserDefined => "user_defined",
+                    };
+
+                    format!("{}/{}", origin, name)
+                })
+                .collect(),
+            quantifier_type: quantifier_type.map(str::to_string),
+            quantifier_value,
+            total_time_ms: result.total_time_ms(),
+            key_stroke_count: key_stroke.whole_count(),
+            ideal_key_stroke_count: result.ideal_key_stroke().whole_count(),
+            miss_count: key_stroke.missed_count(),
+            cpm: scores.cpm(),
+            accuracy: scores.accuracy(),
+            scoring_formula: scores.formula(),
+            score: scores.score(),
+        }
+    }
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.id.map_or_else(String::new, |id| id.to_string()),
+            self.finished_at_ms
+                .map_or_else(String::new, |finished_at_ms| finished_at_ms.to_string()),
+            match self.dictionary_type {
+                DictionaryType::Word => "word".to_string(),
+                DictionaryType::Sentence => "sentence".to_string(),
+            },
+            // 1つの列に収めるために辞書同士はセミコロンで区切る
+            self.dictionaries.join(";"),
+            self.quantifier_type.clone().unwrap_or_default(),
+            self.quantifier_value
+                .map_or_else(String::new, |value| value.to_string()),
+            self.total_time_ms.to_string(),
+            self.key_stroke_count.to_string(),
+            self.ideal_key_stroke_count.to_string(),
+            self.miss_count.to_string(),
+            self.cpm.to_string(),
+            format!("{:.2}", self.accuracy),
+            match self.scoring_formula {
+                ScoringFormula::ETyping => "eTyping".to_string(),
+                ScoringFormula::TypewellRkpm => "typewellRkpm".to_string(),
+                ScoringFormula::NetWpm => "netWpm".to_string(),
+            },
+            format!("{:.2}", self.score),
+        ]
+    }
+}
+
+impl From<&HistoryEntry> for ExportRow {
+    fn from(entry: &HistoryEntry) -> Self {
+        let record = entry.record();
+
+        Self {
+            id: Some(entry.id()),
+            finished_at_ms: Some(entry.finished_at_ms()),
+            ..Self::new(
+                record.dictionary_type(),
+                record.used_dictionaries(),
+                record.quantifier().as_ref(),
+                record.result(),
+            )
+        }
+    }
+}
+
+// 指定された形式でファイルに書き出す
+pub(crate) fn write_rows<P: AsRef<Path>>(
+    path: P,
+    format: ExportFormat,
+    rows: &[ExportRow],
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    match format {
+        ExportFormat::Json => serde_json::to_writer_pretty(&mut writer, rows)?,
+        ExportFormat::Csv => write_csv(&mut writer, rows)?,
+    }
+
+    writer.flush()
+}
+
+// 表計算ソフトで開いた時に文字化けしないようにBOMを付ける
+fn write_csv<W: Write>(writer: &mut W, rows: &[ExportRow]) -> io::Result<()> {
+    write!(writer, "\u{feff}")?;
+    writeln!(writer, "{}", CSV_HEADER.join(","))?;
+
+    for row in rows {
+        let fields: Vec<String> = row
+            .csv_fields()
+            .iter()
+            .map(|field| escape_csv_field(field))
+            .collect();
+
+        writeln!(writer, "{}", fields.join(","))?;
+    }
+
+    Ok(())
+}
+
+// カンマ・ダブルクォート・改行を含む場合にはダブルクォートで囲む
+fn escape_csv_field(field: &str) -> String {
+    if field.contains(|c| matches!(c, ',' | '"' | '\n' | '\r')) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn escape_csv_field_1() {
+        assert_eq!(escape_csv_field("四字熟語"), "四字熟語");
+        assert_eq!(escape_csv_field("a,b"), "\"a,b\"");
+        assert_eq!(escape_csv_field("\"a\""), "\"\"\"a\"\"\"");
+    }
+}
@@ -0,0 +1,488 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use typing_engine::{
+    DisplayInfo, KeyStrokeChar, QueryRequest, TypingEngine, TypingEngineError,
+    TypingResultStatistics,
+};
+
+use crate::display_info::finished_lap_count;
+use crate::query_request::LapRequestFromUI;
+use crate::result::{LapResult, SaveWarning, TypingResult};
+
+// タイピングの進行状態
+// UI側の画面遷移と同じ名前にしている
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum GameState {
+    // クエリが確定していない
+    ModeSelect,
+    // クエリが確定して開始を待っている
+    TransitionToTyping,
+    Typing,
+    // タイピング中に一時停止している
+    Paused,
+    // 最後まで打ち終えたか制限時間が来て結果が確定している
+    Finished,
+}
+
+// タイピングエンジンを包み進行状態に合わない順番での操作を拒否する
+pub(crate) struct GameSession {
+    state: GameState,
+    typing_engine: TypingEngine,
+    result: Option<TypingResult>,
+    paused_intervals: PausedIntervals,
+    // 表示と結果の両方で同じ単位のラップにするためにクエリと一緒に確定させる
+    lap_request: LapRequestFromUI,
+    // 各ラップ末の一時停止していた時間を除いた経過時間とそれまでのミスタイプ数
+    lap_ends: Vec<(Duration, usize)>,
+    // 最後の打鍵の一時停止していた時間を除いた経過時間とそれまでのミスタイプ数
+    last_stroke: Option<(Duration, usize)>,
+}
+
+impl GameSession {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: GameState::ModeSelect,
+            typing_engine: TypingEngine::new(),
+            result: None,
+            paused_intervals: PausedIntervals::default(),
+            lap_request: LapRequestFromUI::default(),
+            lap_ends: vec![],
+            last_stroke: None,
+        }
+    }
+
+    pub(crate) fn state(&self) -> GameState {
+        self.state
+    }
+
+    // クエリを確定させて開始を待つ
+    // タイピング中に新しいクエリで置き換えることはできない
+    pub(crate) fn confirm(
+        &mut self,
+        query_request: QueryRequest,
+        lap_request: LapRequestFromUI,
+    ) -> Result<(), GameSessionError> {
+        self.ensure_state(
+            "confirm",
+            &[
+                GameState::ModeSelect,
+                GameState::TransitionToTyping,
+                GameState::Finished,
+            ],
+        )?;
+
+        self.typing_engine.init(query_request);
+        self.lap_request = lap_request;
+        self.result = None;
+        self.state = GameState::TransitionToTyping;
+
+        Ok(())
+    }
+
+    pub(crate) fn start(&mut self) -> Result<(), GameSessionError> {
+        self.ensure_state("start", &[GameState::TransitionToTyping])?;
+
+        self.typing_engine.start()?;
+        self.paused_intervals = PausedIntervals::started_now();
+        self.lap_ends.clear();
+        self.last_stroke = None;
+        self.state = GameState::Typing;
+
+        Ok(())
+    }
+
+    // 一時停止中は打鍵を受け付けず経過時間にも含めない
+    pub(crate) fn pause(&mut self) -> Result<(), GameSessionError> {
+        self.ensure_state("pause", &[GameState::Typing])?;
+
+        self.paused_intervals.pause();
+        self.state = GameState::Paused;
+
+        Ok(())
+    }
+
+    pub(crate) fn resume(&mut self) -> Result<(), GameSessionError> {
+        self.ensure_state("resume", &[GameState::Paused])?;
+
+        self.paused_intervals.resume();
+        self.state = GameState::Typing;
+
+        Ok(())
+    }
+
+    // 開始から一時停止していた時間を除いた経過時間
+    // UI側の計測は一時停止を考慮しないので制限時間の判定にはこちらを使う
+    pub(crate) fn active_elapsed_time(&self) -> Duration {
+        self.paused_intervals
+            .active_time(self.paused_intervals.elapsed())
+    }
+
+    // 各ラップ末の経過時間をミリ秒で返す
+    pub(crate) fn lap_end_time_ms(&self) -> Vec<f64> {
+        self.lap_ends
+            .iter()
+            .map(|(lap_end_time, _)| lap_end_time.as_millis() as f64)
+            .collect()
+    }
+
+    // 打ち終えたかどうかを返す
+    // 打ち終えても結果を確定させるまではタイピング中のままとする
+    pub(crate) fn stroke_key(
+        &mut self,
+        key_stroke: KeyStrokeChar,
+    ) -> Result<bool, GameSessionError> {
+        self.ensure_state("stroke key", &[GameState::Typing])?;
+
+        let finished = self.typing_engine.stroke_key(key_stroke)?;
+
+        // タイピングエンジンは綴りで区切ったラップの時間を公開していないので単位によらずここで記録する
+        let display_info = self.construct_display_info()?;
+        let key_stroke_info = display_info.key_stroke_info();
+        let lap_count = finished_lap_count(key_stroke_info);
+        let wrong_count = key_stroke_info.on_typing_statistics().wrong_count();
+        let active_time = self
+            .paused_intervals
+            .active_time(self.paused_intervals.elapsed());
+
+        while self.lap_ends.len() < lap_count {
+            self.lap_ends.push((active_time, wrong_count));
+        }
+        self.last_stroke.replace((active_time, wrong_count));
+
+        Ok(finished)
+    }
+
+    pub(crate) fn construct_display_info(&self) -> Result<DisplayInfo, GameSessionError> {
+        self.ensure_state(
+            "construct display info",
+            &[GameState::Typing, GameState::Paused, GameState::Finished],
+        )?;
+
+        Ok(self
+            .typing_engine
+            .construct_display_info(self.lap_request.construct_lap_request())?)
+    }
+
+    // 合計時間は一時停止していた時間を除いたものにする
+    pub(crate) fn construct_result(&self) -> Result<TypingResult, GameSessionError> {
+        self.ensure_state("construct result", &[GameState::Typing])?;
+
+        let result_statistics: TypingResultStatistics = self
+            .typing_engine
+            .construst_result_statistics(self.lap_request.construct_lap_request())?;
+
+        Ok(TypingResult::new(
+            &result_statistics,
+            self.paused_intervals
+                .active_time(result_statistics.total_time()),
+        ))
+    }
+
+    // ラップごとの結果を加えて結果を確定させタイピングを終了する
+    pub(crate) fn finish(&mut self, mut result: TypingResult) -> Result<(), GameSessionError> {
+        self.ensure_state("finish", &[GameState::Typing])?;
+
+        result.set_laps(self.construct_lap_results(result.total_time_ms()));
+        self.result.replace(result);
+        self.state = GameState::Finished;
+
+        Ok(())
+    }
+
+    pub(crate) fn result(&self) -> Result<&TypingResult, GameSessionError> {
+        self.ensure_state("get result", &[GameState::Finished])?;
+
+        Ok(self.result.as_ref().unwrap())
+    }
+
+    // 結果を確定させた後に履歴などの保存に失敗したことを結果に加える
+    pub(crate) fn set_save_warnings(
+        &mut self,
+        save_warnings: Vec<SaveWarning>,
+    ) -> Result<(), GameSessionError> {
+        self.ensure_state("set save warnings", &[GameState::Finished])?;
+
+        self.result
+            .as_mut()
+            .unwrap()
+            .set_save_warnings(save_warnings);
+
+        Ok(())
+    }
+
+    // どの状態からでもクエリの確定前に戻す
+    pub(crate) fn cancel(&mut self) {
+        self.result = None;
+        self.state = GameState::ModeSelect;
+    }
+
+    // 最後のラップ末より後にも打鍵していた場合には終了した時点までを最後のラップとする
+    fn construct_lap_results(&self, total_time_ms: usize) -> Vec<LapResult> {
+        let mut lap_ends: Vec<(usize, usize)> = self
+            .lap_ends
+            .iter()
+            .map(|(lap_end_time, wrong_count)| (lap_end_time.as_millis() as usize, *wrong_count))
+            .collect();
+
+        if let Some((last_stroke_time, wrong_count)) = self.last_stroke {
+            if self
+                .lap_ends
+                .last()
+                .map_or(true, |(lap_end_time, _)| *lap_end_time < last_stroke_time)
+            {
+                lap_ends.push((total_time_ms, wrong_count));
+            }
+        }
+
+        let mut prev_lap_end = (0, 0);
+
+        lap_ends
+            .into_iter()
+            .map(|(lap_end_time_ms, wrong_count)| {
+                let lap_result = LapResult::new(
+                    lap_end_time_ms.saturating_sub(prev_lap_end.0),
+                    wrong_count - prev_lap_end.1,
+                );
+                prev_lap_end = (lap_end_time_ms, wrong_count);
+
+                lap_result
+            })
+            .collect()
+    }
+
+    fn ensure_state(
+        &self,
+        operation: &'static str,
+        allowed_states: &[GameState],
+    ) -> Result<(), GameSessionError> {
+        if allowed_states.contains(&self.state) {
+            Ok(())
+        } else {
+            Err(GameSessionError::InvalidState {
+                operation,
+                state: self.state,
+            })
+        }
+    }
+}
+
+// 一時停止していた区間をタイピングの開始からの経過時間で記録する
+// タイピングエンジンの時計は止められないのでエンジンの経過時間からこの区間を取り除いて使う
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PausedIntervals {
+    started_at: Option<Instant>,
+    paused_at: Option<Duration>,
+    intervals: Vec<(Duration, Duration)>,
+}
+
+impl PausedIntervals {
+    fn started_now() -> Self {
+        Self {
+            started_at: Some(Instant::now()),
+            ..Self::default()
+        }
+    }
+
+    fn pause(&mut self) {
+        self.paused_at.replace(self.elapsed());
+    }
+
+    fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.record(paused_at, self.elapsed());
+        }
+    }
+
+    fn record(&mut self, paused_at: Duration, resumed_at: Duration) {
+        self.intervals.push((paused_at, resumed_at));
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.started_at
+            .map_or(Duration::ZERO, |started_at| started_at.elapsed())
+    }
+
+    // 開始からの経過時間のうち一時停止していなかった時間
+    pub(crate) fn active_time(&self, elapsed: Duration) -> Duration {
+        let paused: Duration = self
+            .intervals
+            .iter()
+            .chain(self.paused_at.as_ref().map(|p| (*p, elapsed)).iter())
+            .filter(|(paused_at, _)| *paused_at < elapsed)
+            .map(|(paused_at, resumed_at)| (*resumed_at).min(elapsed) - *paused_at)
+            .sum();
+
+        elapsed - paused
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum GameSessionError {
+    // 現在の状態では受け付けられない操作
+    InvalidState {
+        operation: &'static str,
+        state: GameState,
+    },
+    Engine(TypingEngineError),
+}
+
+impl From<TypingEngineError> for GameSessionError {
+    fn from(e: TypingEngineError) -> Self {
+        Self::Engine(e)
+    }
+}
+
+impl Display for GameSessionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidState { operation, state } => {
+                write!(f, "cannot {} in {:?} state", operation, state)
+            }
+            Self::Engine(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for GameSessionError {}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+
+    use typing_engine::{VocabularyOrder, VocabularyQuantifier, VocabularySeparator};
+
+    use super::*;
+    use crate::library::parse_vocabulary_entry;
+
+    #[test]
+    fn reject_out_of_order_1() {
+        let vocabulary_entry = parse_vocabulary_entry("犬:いぬ").unwrap();
+        let vocabulary_entries = vec![&vocabulary_entry];
+        let mut game_session = GameSession::new();
+
+        assert!(game_session.start().is_err());
+        assert!(game_session.stroke_key('i'.try_into().unwrap()).is_err());
+
+        game_session
+            .confirm(
+                QueryRequest::new(
+                    &vocabulary_entries,
+                    VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                    VocabularySeparator::None,
+                    VocabularyOrder::InOrder,
+                ),
+                LapRequestFromUI::default(),
+            )
+            .unwrap();
+        game_session.start().unwrap();
+        assert_eq!(game_session.state(), GameState::Typing);
+        assert!(game_session.result().is_err());
+
+        game_session.pause().unwrap();
+        assert!(game_session.stroke_key('i'.try_into().unwrap()).is_err());
+        game_session.resume().unwrap();
+
+        game_session.cancel();
+        assert_eq!(game_session.state(), GameState::ModeSelect);
+        assert!(game_session.stroke_key('i'.try_into().unwrap()).is_err());
+    }
+
+    #[test]
+    fn lap_end_time_1() {
+        let vocabulary_entry = parse_vocabulary_entry("犬:いぬ").unwrap();
+        let vocabulary_entries = vec![&vocabulary_entry];
+        let mut game_session = GameSession::new();
+
+        game_session
+            .confirm(
+                QueryRequest::new(
+                    &vocabulary_entries,
+                    VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                    VocabularySeparator::None,
+                    VocabularyOrder::InOrder,
+                ),
+                LapRequestFromUI::Spell {
+                    count: NonZeroUsize::new(1).unwrap(),
+                },
+            )
+            .unwrap();
+        game_session.start().unwrap();
+
+        game_session.stroke_key('i'.try_into().unwrap()).unwrap();
+        assert_eq!(game_session.lap_end_time_ms().len(), 1);
+
+        game_session.stroke_key('n'.try_into().unwrap()).unwrap();
+        assert_eq!(game_session.lap_end_time_ms().len(), 1);
+
+        game_session.stroke_key('x'.try_into().unwrap()).unwrap();
+        assert_eq!(game_session.lap_end_time_ms().len(), 1);
+
+        assert!(game_session.stroke_key('u'.try_into().unwrap()).unwrap());
+        assert_eq!(game_session.lap_end_time_ms().len(), 2);
+
+        let result = game_session.construct_result().unwrap();
+        game_session.finish(result).unwrap();
+
+        let laps = &serde_json::to_value(game_session.result().unwrap()).unwrap()["laps"];
+        assert_eq!(laps.as_array().unwrap().len(), 2);
+        assert_eq!(laps[0]["missCount"], 0);
+        assert_eq!(laps[1]["missCount"], 1);
+    }
+
+    #[test]
+    fn active_time_1() {
+        let mut paused_intervals = PausedIntervals::default();
+        paused_intervals.record(Duration::from_millis(1000), Duration::from_millis(3000));
+        paused_intervals.record(Duration::from_millis(5000), Duration::from_millis(6000));
+
+        assert_eq!(
+            paused_intervals.active_time(Duration::from_millis(500)),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            paused_intervals.active_time(Duration::from_millis(4000)),
+            Duration::from_millis(2000)
+        );
+        assert_eq!(
+            paused_intervals.active_time(Duration::from_millis(7000)),
+            Duration::from_millis(4000)
+        );
+    }
+
+    #[test]
+    fn active_elapsed_time_1() {
+        let vocabulary_entry = parse_vocabulary_entry("犬:いぬ").unwrap();
+        let vocabulary_entries = vec![&vocabulary_entry];
+        let mut game_session = GameSession::new();
+
+        game_session
+            .confirm(
+                QueryRequest::new(
+                    &vocabulary_entries,
+                    VocabularyQuantifier::Vocabulary(NonZeroUsize::new(1).unwrap()),
+                    VocabularySeparator::None,
+                    VocabularyOrder::InOrder,
+                ),
+                LapRequestFromUI::default(),
+            )
+            .unwrap();
+        game_session.start().unwrap();
+        game_session.pause().unwrap();
+
+        // 一時停止している間は経過時間が進まない
+        let paused_elapsed_time = game_session.active_elapsed_time();
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(game_session.active_elapsed_time(), paused_elapsed_time);
+
+        game_session.resume().unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(
+            game_session.active_elapsed_time() >= paused_elapsed_time + Duration::from_millis(20)
+        );
+    }
+}
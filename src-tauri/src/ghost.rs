@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use typing_engine::DisplayInfo;
+
+// 過去のタイピングを再生して得た各時刻での位置の記録
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Ghost {
+    // 経過時間の昇順に並ぶ
+    positions: Vec<(usize, GhostPosition)>,
+}
+
+impl Ghost {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    // 経過時間が記録済みのものより前の場合には無視する
+    pub(crate) fn push(&mut self, elapsed_time_ms: usize, display_info: &DisplayInfo) {
+        if self
+            .positions
+            .last()
+            .map_or(false, |(last_elapsed_time_ms, _)| {
+                *last_elapsed_time_ms > elapsed_time_ms
+            })
+        {
+            return;
+        }
+
+        let view_info = display_info.view_info();
+
+        self.positions.push((
+            elapsed_time_ms,
+            GhostPosition {
+                // 打ち終えた後はカーソルがないので末尾にいることにする
+                view_position: view_info
+                    .current_cursor_positions()
+                    .first()
+                    .copied()
+                    .unwrap_or_else(|| view_info.view().chars().count()),
+                key_stroke_position: display_info.key_stroke_info().current_cursor_position(),
+            },
+        ));
+    }
+
+    // 指定された経過時間の時点でのゴーストの位置
+    pub(crate) fn position_at(&self, elapsed_time_ms: usize) -> Option<&GhostPosition> {
+        let count = self
+            .positions
+            .partition_point(|(position_elapsed_time_ms, _)| {
+                *position_elapsed_time_ms <= elapsed_time_ms
+            });
+
+        count.checked_sub(1).map(|index| &self.positions[index].1)
+    }
+}
+
+// ある時刻でのゴーストの表示文字列と打鍵列それぞれの上での位置
+// 打鍵列上の位置は入力方法の違いによって打鍵列が異なる場合には近似になる
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct GhostPosition {
+    view_position: usize,
+    key_stroke_position: usize,
+}
@@ -0,0 +1,524 @@
+use std::{
+    cmp::Ordering,
+    fs::{read, read_to_string, OpenOptions},
+    io::{self, Write},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::PathResolver;
+
+use crate::atomic_file::write_atomically;
+use crate::key_miss::KeyMissStatistics;
+use crate::library::{DictionaryOrigin, DictionaryType};
+use crate::query_request::VocabularyQuantifierFromUI;
+use crate::result::TypingResult;
+use crate::vocabulary_tracker::VocabularyResult;
+
+// 終了したタイピングの履歴を保持しアプリケーション用ディレクトリ配下のファイルに永続化する
+// ファイルは1行1履歴のJSON Lines形式で追記していく
+pub(crate) struct History {
+    history_file_path: PathBuf,
+    // 次に追加する履歴のIDを保存するファイル
+    next_id_file_path: PathBuf,
+    entries: Vec<HistoryEntry>,
+    // 読み込めなかった行がある場合にはファイルを書き直すとその行が失われる
+    loaded_completely: bool,
+    // 削除した履歴のIDを使い回すとリプレイなどの紐付けが変わるので単調に増やす
+    next_id: u64,
+}
+
+impl History {
+    pub(crate) fn new(path_resolver: PathResolver) -> Self {
+        Self::open(path_resolver.app_dir().unwrap())
+    }
+
+    fn open(app_dir: PathBuf) -> Self {
+        let history_file_path = app_dir.join("history.jsonl");
+        let next_id_file_path = app_dir.join("history_next_id");
+
+        let (entries, loaded_completely) = load_history_entries(&history_file_path);
+
+        // IDを保存し始める前の履歴やIDのファイルが壊れている場合にも既存のIDとは被らないようにする
+        let next_id = entries
+            .iter()
+            .map(|entry| entry.id + 1)
+            .chain(load_next_id(&next_id_file_path))
+            .max()
+            .unwrap_or(0);
+
+        Self {
+            history_file_path,
+            next_id_file_path,
+            entries,
+            loaded_completely,
+            next_id,
+        }
+    }
+
+    // 履歴を1件追加しファイルに追記する
+    pub(crate) fn append(&mut self, record: HistoryRecord) -> io::Result<&HistoryEntry> {
+        let id = self.next_id;
+        let entry = HistoryEntry {
+            id,
+            finished_at_ms: current_unix_time_ms(),
+            record,
+        };
+
+        // 追記に失敗してもそのIDは使わないように先にIDを進めておく
+        self.next_id = id + 1;
+        write_atomically(&self.next_id_file_path, self.next_id.to_string())?;
+
+        let mut f = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.history_file_path)?;
+        writeln!(f, "{}", serde_json::to_string(&entry)?)?;
+
+        self.entries.push(entry);
+        Ok(self.entries.last().unwrap())
+    }
+
+    // 条件に合う履歴を古い順に列挙する
+    pub(crate) fn filtered_entries(&self, filter: &HistoryFilter) -> Vec<&HistoryEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| filter.matches(entry))
+            .collect()
+    }
+
+    // 条件に合う履歴のキーごとのミスタイプを集計する
+    pub(crate) fn aggregate_key_miss_statistics(
+        &self,
+        filter: &HistoryFilter,
+    ) -> KeyMissStatistics {
+        let mut statistics = KeyMissStatistics::new();
+
+        self.filtered_entries(filter).iter().for_each(|entry| {
+            statistics.merge(&entry.record.key_miss_statistics);
+        });
+
+        statistics
+    }
+
+    // 同じ辞書と終了条件で打った履歴を速い順に列挙する
+    // 辞書の指定順は問わない
+    pub(crate) fn entries_by_speed(
+        &self,
+        dictionary_type: &DictionaryType,
+        used_dictionaries: &[(DictionaryOrigin, String)],
+        quantifier: &VocabularyQuantifierFromUI,
+    ) -> Vec<&HistoryEntry> {
+        let mut entries: Vec<&HistoryEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| {
+                entry.record.dictionary_type == *dictionary_type
+                    && entry.record.used_dictionaries.len() == used_dictionaries.len()
+                    && used_dictionaries
+                        .iter()
+                        .all(|d| entry.record.used_dictionaries.contains(d))
+                    && entry.record.quantifier().as_ref() == Some(quantifier)
+            })
+            .collect();
+
+        entries.sort_by(|a, b| {
+            b.record
+                .result
+                .ideal_key_stroke_speed()
+                .partial_cmp(&a.record.result.ideal_key_stroke_speed())
+                .unwrap_or(Ordering::Equal)
+        });
+
+        entries
+    }
+
+    // 全ての履歴の語彙ごとの結果を列挙する
+    pub(crate) fn vocabulary_results(&self) -> impl Iterator<Item = &VocabularyResult> {
+        self.entries
+            .iter()
+            .flat_map(|entry| entry.record.vocabulary_results.iter())
+    }
+
+    // 指定されたIDの履歴を削除しファイルを書き直す
+    // 削除した件数を返す
+    pub(crate) fn delete(&mut self, ids: &[u64]) -> io::Result<usize> {
+        if !self.loaded_completely {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "history file has unreadable lines, so it is not rewritten",
+            ));
+        }
+
+        let previous_len = self.entries.len();
+        self.entries.retain(|entry| !ids.contains(&entry.id));
+
+        let deleted_count = previous_len - self.entries.len();
+        if deleted_count != 0 {
+            self.rewrite()?;
+        }
+
+        Ok(deleted_count)
+    }
+
+    fn rewrite(&self) -> io::Result<()> {
+        let mut content = String::new();
+        for entry in &self.entries {
+            content.push_str(&serde_json::to_string(entry)?);
+            content.push('\n');
+        }
+
+        write_atomically(&self.history_file_path, content)
+    }
+}
+
+// 保存されている次の履歴のIDを読み込む
+fn load_next_id(next_id_file_path: &Path) -> Option<u64> {
+    read_to_string(next_id_file_path).ok()?.trim().parse().ok()
+}
+
+// 履歴ファイルを読み込み、全ての行を読み込めたかどうかも返す
+// 壊れた行は読み飛ばしてそれ以降の行は読み込む
+fn load_history_entries<P: AsRef<Path>>(history_file_path: P) -> (Vec<HistoryEntry>, bool) {
+    let content = match read(history_file_path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return (vec![], true),
+        Err(_) => return (vec![], false),
+    };
+
+    let mut loaded_completely = true;
+
+    let entries = content
+        .split(|b| *b == b'\n')
+        .filter(|line| !line.iter().all(u8::is_ascii_whitespace))
+        .filter_map(|line| {
+            let entry = serde_json::from_slice::<HistoryEntry>(line).ok();
+            loaded_completely &= entry.is_some();
+
+            entry
+        })
+        .map(|mut entry| {
+            entry.record.result.update_scores();
+            entry
+        })
+        .collect();
+
+    (entries, loaded_completely)
+}
+
+fn current_unix_time_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+        .try_into()
+        .unwrap()
+}
+
+// 保存された1回分のタイピングの履歴
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HistoryEntry {
+    id: u64,
+    // UNIXエポックからのミリ秒
+    finished_at_ms: u64,
+    #[serde(flatten)]
+    record: HistoryRecord,
+}
+
+impl HistoryEntry {
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub(crate) fn finished_at_ms(&self) -> u64 {
+        self.finished_at_ms
+    }
+
+    pub(crate) fn record(&self) -> &HistoryRecord {
+        &self.record
+    }
+}
+
+// 1回分のタイピングで記録する内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HistoryRecord {
+    dictionary_type: DictionaryType,
+    used_dictionaries: Vec<(DictionaryOrigin, String)>,
+    key_stroke_count_threshold: Option<NonZeroUsize>,
+    // 終了条件を記録し始める前の履歴には存在しない
+    #[serde(default)]
+    quantifier: Option<VocabularyQuantifierFromUI>,
+    result: TypingResult,
+    lap_end_time: Vec<f64>,
+    // キーごとのミスタイプの集計を記録し始める前の履歴には存在しない
+    #[serde(default)]
+    key_miss_statistics: KeyMissStatistics,
+    // 語彙ごとの結果を記録し始める前の履歴には存在しない
+    #[serde(default)]
+    vocabulary_results: Vec<VocabularyResult>,
+}
+
+impl HistoryRecord {
+    pub(crate) fn new(
+        dictionary_type: DictionaryType,
+        used_dictionaries: Vec<(DictionaryOrigin, String)>,
+        quantifier: VocabularyQuantifierFromUI,
+        result: TypingResult,
+        lap_end_time: Vec<f64>,
+        key_miss_statistics: KeyMissStatistics,
+        vocabulary_results: Vec<VocabularyResult>,
+    ) -> Self {
+        // 以前の履歴と同じ形でも参照できるように打鍵数の閾値も残しておく
+        let key_stroke_count_threshold = match &quantifier {
+            VocabularyQuantifierFromUI::KeyStroke { count } => Some(*count),
+            _ => None,
+        };
+
+        Self {
+            dictionary_type,
+            used_dictionaries,
+            key_stroke_count_threshold,
+            quantifier: Some(quantifier),
+            result,
+            lap_end_time,
+            key_miss_statistics,
+            vocabulary_results,
+        }
+    }
+
+    pub(crate) fn dictionary_type(&self) -> &DictionaryType {
+        &self.dictionary_type
+    }
+
+    pub(crate) fn used_dictionaries(&self) -> &[(DictionaryOrigin, String)] {
+        &self.used_dictionaries
+    }
+
+    pub(crate) fn result(&self) -> &TypingResult {
+        &self.result
+    }
+
+    // 終了条件を記録し始める前の履歴は打鍵数の閾値から復元する
+    pub(crate) fn quantifier(&self) -> Option<VocabularyQuantifierFromUI> {
+        self.quantifier.clone().or_else(|| {
+            self.key_stroke_count_threshold
+                .map(|count| VocabularyQuantifierFromUI::KeyStroke { count })
+        })
+    }
+}
+
+// 履歴を絞り込む条件
+// 指定されていない条件では絞り込まない
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HistoryFilter {
+    // この時刻（UNIXエポックからのミリ秒）以降に終了したもの
+    from_ms: Option<u64>,
+    // この時刻（UNIXエポックからのミリ秒）より前に終了したもの
+    to_ms: Option<u64>,
+    dictionary_type: Option<DictionaryType>,
+    // この辞書を使用したもの
+    dictionary: Option<(DictionaryOrigin, String)>,
+}
+
+impl HistoryFilter {
+    fn matches(&self, entry: &HistoryEntry) -> bool {
+        self.from_ms
+            .map_or(true, |from| from <= entry.finished_at_ms)
+            && self.to_ms.map_or(true, |to| entry.finished_at_ms < to)
+            && self
+                .dictionary_type
+                .as_ref()
+                .map_or(true, |t| *t == entry.record.dictionary_type)
+            && self
+                .dictionary
+                .as_ref()
+                .map_or(true, |d| entry.record.used_dictionaries.contains(d))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    use super::*;
+
+    fn temporary_app_dir(name: &str) -> PathBuf {
+        let app_dir =
+            std::env::temp_dir().join(format!("tconcierge_{}_{}", name, std::process::id()));
+        create_dir_all(&app_dir).unwrap();
+
+        app_dir
+    }
+
+    fn record(dictionary_type: DictionaryType, dictionary_name: &str) -> HistoryRecord {
+        let result: TypingResult = serde_json::from_value(serde_json::json!({
+            "totalTimeMs": 60000,
+            "keyStroke": { "wholeCount": 300, "completelyCorrectCount": 270, "missedCount": 40 },
+            "idealKeyStroke": { "wholeCount": 280, "completelyCorrectCount": 250, "missedCount": 40 },
+        }))
+        .unwrap();
+
+        HistoryRecord::new(
+            dictionary_type,
+            vec![(DictionaryOrigin::UserDefined, dictionary_name.to_string())],
+            VocabularyQuantifierFromUI::KeyStroke {
+                count: NonZeroUsize::new(300).unwrap(),
+            },
+            result,
+            vec![],
+            KeyMissStatistics::new(),
+            vec![],
+        )
+    }
+
+    fn entry(
+        finished_at_ms: u64,
+        dictionary_type: DictionaryType,
+        dictionary_name: &str,
+    ) -> HistoryEntry {
+        HistoryEntry {
+            id: 0,
+            finished_at_ms,
+            record: record(dictionary_type, dictionary_name),
+        }
+    }
+
+    fn ids(history: &History) -> Vec<u64> {
+        history
+            .filtered_entries(&HistoryFilter::default())
+            .iter()
+            .map(|entry| entry.id())
+            .collect()
+    }
+
+    #[test]
+    fn history_filter_1() {
+        let entry = entry(1000, DictionaryType::Word, "四字熟語");
+
+        assert!(HistoryFilter::default().matches(&entry));
+
+        let filter =
+            |json: serde_json::Value| -> HistoryFilter { serde_json::from_value(json).unwrap() };
+
+        // 開始時刻は含み終了時刻は含まない
+        assert!(filter(serde_json::json!({ "fromMs": 1000 })).matches(&entry));
+        assert!(!filter(serde_json::json!({ "fromMs": 1001 })).matches(&entry));
+        assert!(filter(serde_json::json!({ "toMs": 1001 })).matches(&entry));
+        assert!(!filter(serde_json::json!({ "toMs": 1000 })).matches(&entry));
+
+        assert!(filter(serde_json::json!({ "dictionaryType": "word" })).matches(&entry));
+        assert!(!filter(serde_json::json!({ "dictionaryType": "sentence" })).matches(&entry));
+
+        assert!(
+            filter(serde_json::json!({ "dictionary": ["user_defined", "四字熟語"] }))
+                .matches(&entry)
+        );
+        assert!(
+            !filter(serde_json::json!({ "dictionary": ["builtin", "四字熟語"] })).matches(&entry)
+        );
+
+        // 全ての条件を満たす必要がある
+        assert!(
+            !filter(serde_json::json!({ "fromMs": 0, "dictionaryType": "sentence" }))
+                .matches(&entry)
+        );
+    }
+
+    #[test]
+    fn append_and_delete_1() {
+        let app_dir = temporary_app_dir("append_and_delete_1");
+
+        let mut history = History::open(app_dir.clone());
+        history
+            .append(record(DictionaryType::Word, "四字熟語"))
+            .unwrap();
+        history
+            .append(record(DictionaryType::Sentence, "名言"))
+            .unwrap();
+        history
+            .append(record(DictionaryType::Word, "IT用語"))
+            .unwrap();
+        assert_eq!(ids(&history), vec![0, 1, 2]);
+
+        assert_eq!(history.delete(&[1, 2, 5]).unwrap(), 2);
+        assert_eq!(history.delete(&[1]).unwrap(), 0);
+
+        // 削除した最新のIDは使い回さない
+        history
+            .append(record(DictionaryType::Word, "四字熟語"))
+            .unwrap();
+        assert_eq!(ids(&history), vec![0, 3]);
+
+        // 読み込み直しても同じ履歴とIDの続きになる
+        let mut reloaded = History::open(app_dir.clone());
+        assert_eq!(ids(&reloaded), vec![0, 3]);
+        assert_eq!(
+            reloaded.filtered_entries(&HistoryFilter::default())[1]
+                .record()
+                .used_dictionaries(),
+            &[(DictionaryOrigin::UserDefined, "四字熟語".to_string())]
+        );
+
+        reloaded.delete(&[3]).unwrap();
+        let mut reloaded = History::open(app_dir.clone());
+        reloaded
+            .append(record(DictionaryType::Word, "四字熟語"))
+            .unwrap();
+
+        remove_dir_all(&app_dir).unwrap();
+
+        assert_eq!(ids(&reloaded), vec![0, 4]);
+    }
+
+    #[test]
+    fn load_history_entries_1() {
+        let app_dir = temporary_app_dir("load_history_entries_1");
+
+        let mut history = History::open(app_dir.clone());
+        history
+            .append(record(DictionaryType::Word, "四字熟語"))
+            .unwrap();
+        history
+            .append(record(DictionaryType::Word, "IT用語"))
+            .unwrap();
+        history
+            .append(record(DictionaryType::Word, "名言"))
+            .unwrap();
+
+        // 書き込み途中で終了した行・UTF-8として不正な行・形式の異なる行を混ぜる
+        let history_file_path = app_dir.join("history.jsonl");
+        let content = read(&history_file_path).unwrap();
+        let mut lines: Vec<&[u8]> = content.split(|b| *b == b'\n').collect();
+        lines.insert(2, b"{\"id\":7,\"finishedAtMs\":");
+        lines.insert(1, b"\xff\xfe");
+        lines.insert(1, b"not json");
+        write(&history_file_path, lines.join(&b'\n')).unwrap();
+
+        // IDのファイルが壊れていても既存の履歴のIDからは続ける
+        write(app_dir.join("history_next_id"), "broken").unwrap();
+
+        let mut reloaded = History::open(app_dir.clone());
+        assert_eq!(ids(&reloaded), vec![0, 1, 2]);
+        reloaded
+            .append(record(DictionaryType::Word, "四字熟語"))
+            .unwrap();
+        assert_eq!(ids(&reloaded), vec![0, 1, 2, 3]);
+
+        // 書き直すと読み込めなかった行が失われるので削除しない
+        assert!(reloaded.delete(&[0]).is_err());
+        assert_eq!(ids(&reloaded), vec![0, 1, 2, 3]);
+
+        let content_after_delete = read(&history_file_path).unwrap();
+
+        remove_dir_all(&app_dir).unwrap();
+
+        assert!(content_after_delete
+            .windows(2)
+            .any(|window| window == b"\xff\xfe"));
+    }
+}
@@ -0,0 +1,109 @@
+use typing_engine::SpellString;
+
+// 半角カタカナ（U+FF66〜U+FF9D）に対応する全角カタカナ
+const HALF_WIDTH_KATAKANA: &str =
+    "ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン";
+
+// ひらがな・カタカナ・長音記号
+pub(crate) fn is_kana(c: char) -> bool {
+    matches!(c, '\u{3041}'..='\u{3096}' | '\u{30a1}'..='\u{30fa}' | 'ー')
+}
+
+// カタカナをひらがなにし、それ以外の文字はそのままにする
+pub(crate) fn katakana_to_hiragana(c: char) -> char {
+    match c {
+        '\u{30a1}'..='\u{30f6}' => char::from_u32(c as u32 - 0x60).unwrap(),
+        _ => c,
+    }
+}
+
+// ひらがなの濁音
+pub(crate) fn voiced(c: char) -> Option<char> {
+    match c {
+        'か' | 'き' | 'く' | 'け' | 'こ' | 'さ' | 'し' | 'す' | 'せ' | 'そ' | 'た' | 'ち'
+        | 'つ' | 'て' | 'と' | 'は' | 'ひ' | 'ふ' | 'へ' | 'ほ' => {
+            char::from_u32(c as u32 + 1)
+        }
+        'う' => Some('ゔ'),
+        _ => None,
+    }
+}
+
+// ひらがなの半濁音
+pub(crate) fn semi_voiced(c: char) -> Option<char> {
+    match c {
+        'は' | 'ひ' | 'ふ' | 'へ' | 'ほ' => char::from_u32(c as u32 + 2),
+        _ => None,
+    }
+}
+
+// 綴りに使えない文字のうち使える文字に置き換えられるものを置き換える
+// カタカナ・半角カタカナはひらがなに、全角英数字・記号・空白は半角にする
+// 全角の記号でも綴りに使えるものはそのまま使う
+pub(crate) fn normalize_spell(spell: &str) -> String {
+    let mut normalized = String::new();
+
+    for c in spell.chars() {
+        match c {
+            // 半角の濁点・半濁点は直前の文字と合成する
+            '\u{ff9e}' | '\u{ff9f}' => {
+                let composed = normalized.chars().last().and_then(|last| {
+                    if c == '\u{ff9e}' {
+                        voiced(last)
+                    } else {
+                        semi_voiced(last)
+                    }
+                });
+
+                match composed {
+                    Some(composed) => {
+                        normalized.pop();
+                        normalized.push(composed);
+                    }
+                    None => normalized.push(c),
+                }
+            }
+            _ => normalized.push(normalize_char(c)),
+        }
+    }
+
+    normalized
+}
+
+fn normalize_char(c: char) -> char {
+    match c {
+        // 全角の空白はキーボードで打てないので綴りに使えるかによらず置き換える
+        '\u{3000}' => ' ',
+        _ if SpellString::try_from(c.to_string()).is_ok() => c,
+        '\u{ff01}'..='\u{ff5e}' => char::from_u32(c as u32 - 0xfee0).unwrap(),
+        '\u{ff66}'..='\u{ff9d}' => katakana_to_hiragana(
+            HALF_WIDTH_KATAKANA
+                .chars()
+                .nth((c as u32 - 0xff66) as usize)
+                .unwrap(),
+        ),
+        '｡' => '。',
+        '｢' => '「',
+        '｣' => '」',
+        '､' => '、',
+        '･' => '・',
+        _ => katakana_to_hiragana(c),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normalize_spell_1() {
+        assert_eq!(normalize_spell("カタカナ"), "かたかな");
+        assert_eq!(normalize_spell("ｶﾞｯﾂﾎﾟｰｽﾞ"), "がっつぽーず");
+        assert_eq!(normalize_spell("ｳﾞｧ"), "ゔぁ");
+        assert_eq!(normalize_spell("ＡＢＣ１２３！［］"), "ABC123！[]");
+        assert_eq!(normalize_spell("ｱﾟ"), "あ\u{ff9f}");
+        assert_eq!(normalize_spell("ひらがな"), "ひらがな");
+        assert_eq!(normalize_spell("ＮＥＷ\u{3000}ＹＯＲＫ"), "NEW YORK");
+        assert!(SpellString::try_from(normalize_spell("にゅー\u{3000}よーく")).is_ok());
+    }
+}
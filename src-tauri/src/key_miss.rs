@@ -0,0 +1,278 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+// JISキーボードの文字キーの配列
+// シフトを押して入力する文字は押されたキーの文字に正規化して集計する
+const JIS_KEYBOARD_ROWS: [&str; 5] = [
+    "1234567890-^\\",
+    "qwertyuiop@[",
+    "asdfghjkl;:]",
+    "zxcvbnm,./_",
+    " ",
+];
+
+// キーごとのミスタイプの集計
+// 文字はシフトの有無を区別して実際に入力された文字のまま集計する
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct KeyMissStatistics {
+    // キーごとの打鍵数（ミスタイプも含む）
+    // 正しい打鍵は実際に打たれたキー、ミスタイプは期待されていたキーで数える
+    stroke_counts: BTreeMap<char, usize>,
+    // 期待されていたキーごとのミスタイプ数
+    miss_counts: BTreeMap<char, usize>,
+    // ミスタイプで実際に打たれたキーごとの回数
+    wrong_key_counts: BTreeMap<char, usize>,
+    // 期待されていたキー -> 実際に打たれたキー -> 回数
+    confusions: BTreeMap<char, BTreeMap<char, usize>>,
+}
+
+impl KeyMissStatistics {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    // 1打鍵分を記録する
+    // 別の綴りで正しく打った場合（ex. 「し」を「shi」ではなく「si」）には表示されていたキーとは異なるキーを打っている
+    pub(crate) fn record(&mut self, expected: char, actual: char, is_correct: bool) {
+        if is_correct {
+            *self.stroke_counts.entry(actual).or_default() += 1;
+        } else {
+            *self.stroke_counts.entry(expected).or_default() += 1;
+            *self.miss_counts.entry(expected).or_default() += 1;
+            *self.wrong_key_counts.entry(actual).or_default() += 1;
+            *self
+                .confusions
+                .entry(expected)
+                .or_default()
+                .entry(actual)
+                .or_default() += 1;
+        }
+    }
+
+    // 他の集計を足し合わせる
+    pub(crate) fn merge(&mut self, other: &Self) {
+        merge_counts(&mut self.stroke_counts, &other.stroke_counts);
+        merge_counts(&mut self.miss_counts, &other.miss_counts);
+        merge_counts(&mut self.wrong_key_counts, &other.wrong_key_counts);
+        other.confusions.iter().for_each(|(expected, actuals)| {
+            merge_counts(self.confusions.entry(*expected).or_default(), actuals);
+        });
+    }
+
+    // キーボードの形に並べたヒートマップ用のデータを構築する
+    pub(crate) fn construct_heat_map(&self) -> KeyMissHeatMap {
+        let mut cells: BTreeMap<char, KeyMissHeatMapCell> = JIS_KEYBOARD_ROWS
+            .iter()
+            .flat_map(|row| row.chars())
+            .map(|key| (key, KeyMissHeatMapCell::new(key)))
+            .collect();
+
+        add_counts_to_cells(&mut cells, &self.stroke_counts, |cell| {
+            &mut cell.stroke_count
+        });
+        add_counts_to_cells(&mut cells, &self.miss_counts, |cell| &mut cell.miss_count);
+        add_counts_to_cells(&mut cells, &self.wrong_key_counts, |cell| {
+            &mut cell.wrong_key_count
+        });
+
+        self.confusions.iter().for_each(|(expected, actuals)| {
+            if let Some(cell) = cells.get_mut(&normalize_to_base_key(*expected)) {
+                actuals.iter().for_each(|(actual, count)| {
+                    *cell
+                        .confusion_counts
+                        .entry(normalize_to_base_key(*actual))
+                        .or_default() += count;
+                });
+            }
+        });
+
+        KeyMissHeatMap {
+            rows: JIS_KEYBOARD_ROWS
+                .iter()
+                .map(|row| {
+                    row.chars()
+                        .map(|key| cells.remove(&key).unwrap().finalize())
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+}
+
+// 文字ごとの回数をキーボードの各キーに足し込む
+fn add_counts_to_cells(
+    cells: &mut BTreeMap<char, KeyMissHeatMapCell>,
+    counts: &BTreeMap<char, usize>,
+    field: fn(&mut KeyMissHeatMapCell) -> &mut usize,
+) {
+    counts.iter().for_each(|(c, count)| {
+        if let Some(cell) = cells.get_mut(&normalize_to_base_key(*c)) {
+            *field(cell) += count;
+        }
+    });
+}
+
+fn merge_counts(to: &mut BTreeMap<char, usize>, from: &BTreeMap<char, usize>) {
+    from.iter().for_each(|(c, count)| {
+        *to.entry(*c).or_default() += count;
+    });
+}
+
+// シフトを押しながら入力する文字をJISキーボードで押されたキーの文字に変換する
+fn normalize_to_base_key(c: char) -> char {
+    match c {
+        'A'..='Z' => c.to_ascii_lowercase(),
+        '!' => '1',
+        '"' => '2',
+        '#' => '3',
+        '$' => '4',
+        '%' => '5',
+        '&' => '6',
+        '\'' => '7',
+        '(' => '8',
+        ')' => '9',
+        '=' => '-',
+        '~' => '^',
+        '|' => '\\',
+        '`' => '@',
+        '{' => '[',
+        '+' => ';',
+        '*' => ':',
+        '}' => ']',
+        '<' => ',',
+        '>' => '.',
+        '?' => '/',
+        _ => c,
+    }
+}
+
+// キーボードの各行を並べたヒートマップ用のデータ
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct KeyMissHeatMap {
+    rows: Vec<Vec<KeyMissHeatMapCell>>,
+}
+
+// ヒートマップの1キー分
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct KeyMissHeatMapCell {
+    key: char,
+    // このキーを打つべきだった回数
+    stroke_count: usize,
+    // このキーを打つべきところでミスタイプした回数
+    miss_count: usize,
+    // ミスタイプでこのキーを打ってしまった回数
+    wrong_key_count: usize,
+    miss_rate: f64,
+    // このキーを打つべきところで代わりに打たれたキーと回数（回数の多い順）
+    confusions: Vec<(char, usize)>,
+    #[serde(skip)]
+    confusion_counts: BTreeMap<char, usize>,
+}
+
+impl KeyMissHeatMapCell {
+    fn new(key: char) -> Self {
+        Self {
+            key,
+            stroke_count: 0,
+            miss_count: 0,
+            wrong_key_count: 0,
+            miss_rate: 0.0,
+            confusions: vec![],
+            confusion_counts: BTreeMap::new(),
+        }
+    }
+
+    fn finalize(mut self) -> Self {
+        self.miss_rate = if self.stroke_count == 0 {
+            0.0
+        } else {
+            self.miss_count as f64 / self.stroke_count as f64
+        };
+
+        self.confusions = self
+            .confusion_counts
+            .iter()
+            .map(|(c, count)| (*c, *count))
+            .collect();
+        self.confusions
+            .sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn construct_heat_map_1() {
+        let mut statistics = KeyMissStatistics::new();
+        statistics.record('k', 'k', true);
+        statistics.record('k', 'l', false);
+        statistics.record('K', 'L', false);
+        statistics.record('k', 'j', false);
+
+        let heat_map = statistics.construct_heat_map();
+        let k = heat_map.rows[2]
+            .iter()
+            .find(|cell| cell.key == 'k')
+            .unwrap();
+        let l = heat_map.rows[2]
+            .iter()
+            .find(|cell| cell.key == 'l')
+            .unwrap();
+
+        assert_eq!(k.stroke_count, 4);
+        assert_eq!(k.miss_count, 3);
+        assert_eq!(k.miss_rate, 0.75);
+        assert_eq!(k.confusions, vec![('l', 2), ('j', 1)]);
+        assert_eq!(l.wrong_key_count, 2);
+    }
+
+    #[test]
+    fn merge_1() {
+        let mut a = KeyMissStatistics::new();
+        a.record('a', 's', false);
+
+        let mut b = KeyMissStatistics::new();
+        b.record('a', 's', false);
+        b.record('a', 'a', true);
+
+        a.merge(&b);
+
+        assert_eq!(a.stroke_counts.get(&'a'), Some(&3));
+        assert_eq!(a.miss_counts.get(&'a'), Some(&2));
+        assert_eq!(a.confusions[&'a'].get(&'s'), Some(&2));
+    }
+
+    #[test]
+    fn record_alternative_spelling_1() {
+        // 「し」を表示されている「shi」ではなく「si」と打った場合には2打鍵目で「h」が期待されていたが「i」を打っている
+        let mut statistics = KeyMissStatistics::new();
+        statistics.record('s', 's', true);
+        statistics.record('h', 'i', true);
+
+        assert_eq!(statistics.stroke_counts.get(&'h'), None);
+        assert_eq!(statistics.stroke_counts.get(&'i'), Some(&1));
+        assert_eq!(statistics.miss_counts, BTreeMap::new());
+
+        let heat_map = statistics.construct_heat_map();
+        let cell = |key: char| {
+            heat_map
+                .rows
+                .iter()
+                .flatten()
+                .find(|cell| cell.key == key)
+                .unwrap()
+        };
+
+        assert_eq!(cell('h').stroke_count, 0);
+        assert_eq!(cell('i').stroke_count, 1);
+        assert_eq!(cell('i').miss_rate, 0.0);
+    }
+}
@@ -1,23 +1,36 @@
 use std::{
-    collections::{HashMap, VecDeque},
-    fs::{create_dir, File},
+    collections::{hash_map::DefaultHasher, BTreeSet, HashMap, HashSet, VecDeque},
+    error::Error,
+    fmt::Display,
+    fs::{canonicalize, create_dir, create_dir_all, read_dir, read_to_string, File},
+    hash::{Hash, Hasher},
     io::Read,
     num::NonZeroUsize,
-    path::{Path, PathBuf},
+    ops::Range,
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
 };
 
 use serde::{Deserialize, Serialize};
-use tauri::{
-    api::dir::{is_dir, read_dir},
-    PathResolver,
-};
+use tauri::PathResolver;
 use typing_engine::{SpellString, VocabularyEntry, VocabularySpellElement};
 
+use crate::atomic_file::write_atomically;
+use crate::kana::normalize_spell;
+
+// 辞書ファイルのヘッダーの始まりと終わりを表す行
+const HEADER_DELIMITER: &str = "---";
+// これ以降から行末までをコメントとする文字
+const COMMENT_MARKER: char = '#';
+
 pub struct Library {
     user_defined_library_dir: PathBuf,
     builtin_library_dir: PathBuf,
     word_dictionaries: HashMap<(DictionaryOrigin, String), Dictionary>,
     sentence_dictionaries: HashMap<(DictionaryOrigin, String), Dictionary>,
+    // 綴りのカタカナなどを綴りに使える文字に置き換えてから読み込む
+    spell_normalization: bool,
+    settings_file_path: PathBuf,
 }
 
 impl Library {
@@ -25,6 +38,10 @@ impl Library {
         let mut user_defined_library_dir = path_resolver.app_dir().unwrap();
         user_defined_library_dir.push("library");
 
+        let mut settings_file_path = path_resolver.app_dir().unwrap();
+        settings_file_path.push("library_settings.json");
+        let settings = load_library_settings(&settings_file_path);
+
         let builtin_library_dir = path_resolver
             .resolve_resource("../builtin_dictionary/")
             .unwrap();
@@ -35,27 +52,17 @@ impl Library {
 
         assert!(user_defined_library_dir.exists());
 
-        let dictionaries: HashMap<(DictionaryOrigin, String), Dictionary> =
-            construct_dictionaries(&user_defined_library_dir, &builtin_library_dir)
-                .drain(..)
-                .map(|dictionary| {
-                    (
-                        (dictionary.origin.clone(), dictionary.name().to_string()),
-                        dictionary,
-                    )
-                })
-                .collect();
-
-        let (word_dictionaries, sentence_dictionaries) = dictionaries
-            .into_iter()
-            .partition(|(_, dictionary)| dictionary.dictionary_type == DictionaryType::Word);
-
-        Self {
-            word_dictionaries,
-            sentence_dictionaries,
+        let mut library = Self {
+            word_dictionaries: HashMap::new(),
+            sentence_dictionaries: HashMap::new(),
             user_defined_library_dir,
             builtin_library_dir,
-        }
+            spell_normalization: settings.spell_normalization,
+            settings_file_path,
+        };
+        library.reload_dictionaries();
+
+        library
     }
 
     // 現在登録されている辞書情報の一覧を取得する
@@ -73,58 +80,313 @@ impl Library {
     }
 
     // 自身の管理している辞書群を更新する
-    pub fn reload_dictionaries(&mut self) {
-        // TODO タイムスタンプで更新のいらないファイルは更新しないようにする
-        let dictionaries: HashMap<(DictionaryOrigin, String), Dictionary> =
-            construct_dictionaries(&self.user_defined_library_dir, &self.builtin_library_dir)
-                .drain(..)
-                .map(|dictionary| {
-                    (
-                        (dictionary.origin.clone(), dictionary.name().to_string()),
-                        dictionary,
-                    )
-                })
-                .collect();
+    // 前回から更新されていないファイルはパースし直さずに使い、辞書の一覧や内容が変わったかどうかを返す
+    pub fn reload_dictionaries(&mut self) -> bool {
+        let dictionary_files = scan_dictionary_files(&self.library_dirs());
+
+        self.reload_dictionaries_from(dictionary_files)
+    }
+
+    // 辞書ファイルを探すディレクトリ
+    // ディレクトリの走査中にライブラリをロックし続けなくて済むようにscan_dictionary_filesと合わせて使う
+    pub fn library_dirs(&self) -> LibraryDirs {
+        LibraryDirs {
+            user_defined: self.user_defined_library_dir.clone(),
+            builtin: self.builtin_library_dir.clone(),
+        }
+    }
+
+    // 走査済みの辞書ファイルの一覧から辞書群を更新する
+    pub fn reload_dictionaries_from(&mut self, dictionary_files: Vec<DictionaryFile>) -> bool {
+        let mut previous_dictionaries: HashMap<PathBuf, Dictionary> = self
+            .word_dictionaries
+            .drain()
+            .chain(self.sentence_dictionaries.drain())
+            .map(|(_, dictionary)| (dictionary.path.clone(), dictionary))
+            .collect();
+        let mut changed = false;
+        let spell_normalization = self.spell_normalization;
+
+        let dictionaries: Vec<Dictionary> = dictionary_files
+            .into_iter()
+            .filter_map(|DictionaryFile { origin, name, path }| {
+                let previous_dictionary = previous_dictionaries.remove(&path);
+                let had_previous_dictionary = previous_dictionary.is_some();
+
+                match Dictionary::load(
+                    &path,
+                    name,
+                    origin,
+                    previous_dictionary,
+                    spell_normalization,
+                ) {
+                    Some((dictionary, reparsed)) => {
+                        changed |= reparsed;
+                        Some(dictionary)
+                    }
+                    // 読めなくなった辞書は一覧から消える
+                    None => {
+                        changed |= had_previous_dictionary;
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        // 削除されたファイルの辞書が残っている
+        changed |= !previous_dictionaries.is_empty();
 
         let (word_dictionaries, sentence_dictionaries) = dictionaries
             .into_iter()
+            .map(|dictionary| {
+                (
+                    (dictionary.origin.clone(), dictionary.name().to_string()),
+                    dictionary,
+                )
+            })
             .partition(|(_, dictionary)| dictionary.dictionary_type == DictionaryType::Word);
 
         self.word_dictionaries = word_dictionaries;
         self.sentence_dictionaries = sentence_dictionaries;
+
+        changed
     }
 
-    // 指定された辞書から語彙群を構成する
+    // 綴りの置き換えをするかどうかを切り替えて設定を保存し全ての辞書を読み込み直す
+    // 切り替わったかどうかを返す
+    pub fn set_spell_normalization(
+        &mut self,
+        spell_normalization: bool,
+    ) -> Result<bool, LibraryError> {
+        if self.spell_normalization == spell_normalization {
+            return Ok(false);
+        }
+
+        let settings = LibrarySettings {
+            spell_normalization,
+        };
+        write_atomically(
+            &self.settings_file_path,
+            serde_json::to_string(&settings).unwrap(),
+        )
+        .map_err(|e| LibraryError::Io(self.settings_file_path.clone(), e.to_string()))?;
+
+        self.spell_normalization = spell_normalization;
+
+        // 更新されていないファイルも読み込み直させる
+        self.word_dictionaries.clear();
+        self.sentence_dictionaries.clear();
+        self.reload_dictionaries();
+
+        Ok(true)
+    }
+
+    // 指定された辞書からタグで絞り込んだ語彙群を構成する
     // 語彙の順番は引数で渡された辞書名の順番になる
     pub fn vocabulary_entries_of_request(
         &self,
         request_dictionary_type: DictionaryType,
         request_dictionaries: &[(DictionaryOrigin, impl AsRef<str>)],
-    ) -> Vec<&VocabularyEntry> {
+        tag_filter: &TagFilter,
+    ) -> Result<Vec<&VocabularyEntry>, LibraryError> {
         let mut vocabulary_entries: Vec<&VocabularyEntry> = vec![];
 
-        let dictionaries = match request_dictionary_type {
-            DictionaryType::Word => &self.word_dictionaries,
-            DictionaryType::Sentence => &self.sentence_dictionaries,
-        };
-
-        request_dictionaries.iter().for_each(|dictionary_info| {
-            let dictionary = dictionaries
-                .get(&(
-                    dictionary_info.0.clone(),
-                    dictionary_info.1.as_ref().to_string(),
-                ))
-                .unwrap();
+        for (dictionary_origin, dictionary_name) in request_dictionaries {
+            let dictionary = self
+                .get_dictionary(
+                    dictionary_name.as_ref(),
+                    dictionary_origin.clone(),
+                    request_dictionary_type.clone(),
+                )
+                .ok_or_else(|| {
+                    LibraryError::DictionaryNotFound(
+                        dictionary_origin.clone(),
+                        dictionary_name.as_ref().to_string(),
+                    )
+                })?;
 
             dictionary
                 .vocabulary_entries
                 .iter()
-                .for_each(|vocabulary_entry| {
-                    vocabulary_entries.push(&vocabulary_entry);
+                .zip(dictionary.vocabulary_tags.iter())
+                .filter(|(_, tags)| tag_filter.matches(tags))
+                .for_each(|(vocabulary_entry, _)| {
+                    vocabulary_entries.push(vocabulary_entry);
                 });
-        });
+        }
+
+        Ok(vocabulary_entries)
+    }
+
+    // 登録されている全ての辞書の語彙
+    pub fn all_vocabulary_entries(&self) -> Vec<&VocabularyEntry> {
+        self.word_dictionaries
+            .values()
+            .chain(self.sentence_dictionaries.values())
+            .flat_map(|dictionary| dictionary.vocabulary_entries())
+            .collect()
+    }
+
+    // 外部の辞書ファイルを検証した上でユーザー定義辞書としてライブラリ直下にコピーする
+    // 有効な語彙が1つもない場合にはコピーしない
+    pub fn import_dictionary<P: AsRef<Path>>(
+        &mut self,
+        source_path: P,
+        conflict_resolution: ImportConflictResolution,
+    ) -> Result<ImportDictionaryResult, LibraryError> {
+        let source_path = source_path.as_ref();
+
+        let (file_stem, extension) = match (
+            source_path.file_stem().and_then(|stem| stem.to_str()),
+            source_path
+                .extension()
+                .and_then(|extension| extension.to_str()),
+        ) {
+            (Some(file_stem), Some(extension))
+                if extension == "tconciergew" || extension == "tconcierges" =>
+            {
+                (file_stem, extension)
+            }
+            _ => return Err(LibraryError::InvalidDictionaryFile(source_path.to_owned())),
+        };
+
+        let content = read_to_string(source_path)
+            .map_err(|e| LibraryError::Io(source_path.to_owned(), e.to_string()))?;
+
+        let (vocabulary_entries, diagnostics) =
+            parse_dictionary_content(&content, self.spell_normalization);
+        let (diagnostics, notices) = split_notices(diagnostics);
 
-        vocabulary_entries
+        if vocabulary_entries.is_empty() {
+            return Err(LibraryError::NoValidVocabulary(source_path.to_owned()));
+        }
+
+        let mut destination_path = self
+            .user_defined_library_dir
+            .join(format!("{}.{}", file_stem, extension));
+
+        let action = if !destination_path.exists() {
+            ImportAction::Imported
+        } else {
+            match conflict_resolution {
+                ImportConflictResolution::Rename => {
+                    destination_path = (2..)
+                        .map(|number| {
+                            self.user_defined_library_dir
+                                .join(numbered_file_name(file_stem, extension, number))
+                        })
+                        .find(|path| !path.exists())
+                        .unwrap();
+
+                    ImportAction::Renamed
+                }
+                ImportConflictResolution::Overwrite => ImportAction::Overwritten,
+                ImportConflictResolution::Skip => ImportAction::Skipped,
+            }
+        };
+
+        if action != ImportAction::Skipped {
+            write_atomically(&destination_path, &content)
+                .map_err(|e| LibraryError::Io(destination_path.clone(), e.to_string()))?;
+
+            self.reload_dictionaries();
+        }
+
+        Ok(ImportDictionaryResult {
+            name: dictionary_name_of(&self.user_defined_library_dir, &destination_path)
+                .unwrap_or_default(),
+            action,
+            valid_vocabulary_count: vocabulary_entries.len(),
+            diagnostics,
+            notices,
+        })
+    }
+
+    // 空のユーザー定義辞書を作成する
+    // 辞書名に/を含む場合にはサブディレクトリに作成する
+    pub fn create_dictionary(
+        &mut self,
+        dictionary_name: &str,
+        dictionary_type: DictionaryType,
+    ) -> Result<DictionaryInfo, LibraryError> {
+        let path = dictionary_path_of(
+            &self.user_defined_library_dir,
+            dictionary_name,
+            &dictionary_type,
+        )
+        .ok_or_else(|| LibraryError::InvalidDictionaryName(dictionary_name.to_string()))?;
+
+        if path.exists() {
+            return Err(LibraryError::DictionaryAlreadyExists(
+                dictionary_name.to_string(),
+            ));
+        }
+
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent).map_err(|e| LibraryError::Io(path.clone(), e.to_string()))?;
+        }
+
+        write_atomically(&path, "").map_err(|e| LibraryError::Io(path.clone(), e.to_string()))?;
+
+        self.reload_dictionaries();
+        self.dictionary_info_of(
+            dictionary_name,
+            DictionaryOrigin::UserDefined,
+            dictionary_type,
+        )
+    }
+
+    // ユーザー定義辞書の語彙を1つ追加・更新・削除してファイルに書き戻す
+    // 組み込み辞書は編集できない
+    pub fn edit_dictionary(
+        &mut self,
+        dictionary_name: &str,
+        dictionary_origin: DictionaryOrigin,
+        dictionary_type: DictionaryType,
+        edit: DictionaryEdit,
+    ) -> Result<DictionaryInfo, LibraryError> {
+        if dictionary_origin == DictionaryOrigin::Builtin {
+            return Err(LibraryError::ReadOnlyDictionary(
+                dictionary_name.to_string(),
+            ));
+        }
+
+        let path = self
+            .get_dictionary(
+                dictionary_name,
+                dictionary_origin.clone(),
+                dictionary_type.clone(),
+            )
+            .map(|dictionary| dictionary.path.clone())
+            .ok_or_else(|| {
+                LibraryError::DictionaryNotFound(
+                    dictionary_origin.clone(),
+                    dictionary_name.to_string(),
+                )
+            })?;
+
+        let content =
+            read_to_string(&path).map_err(|e| LibraryError::Io(path.clone(), e.to_string()))?;
+        let content = edit.apply(dictionary_name, &content, self.spell_normalization)?;
+
+        write_atomically(&path, &content)
+            .map_err(|e| LibraryError::Io(path.clone(), e.to_string()))?;
+
+        self.reload_dictionaries();
+        self.dictionary_info_of(dictionary_name, dictionary_origin, dictionary_type)
+    }
+
+    fn dictionary_info_of(
+        &self,
+        dictionary_name: &str,
+        dictionary_origin: DictionaryOrigin,
+        dictionary_type: DictionaryType,
+    ) -> Result<DictionaryInfo, LibraryError> {
+        self.get_dictionary(dictionary_name, dictionary_origin.clone(), dictionary_type)
+            .map(Dictionary::construct_dictionary_info)
+            .ok_or_else(|| {
+                LibraryError::DictionaryNotFound(dictionary_origin, dictionary_name.to_string())
+            })
     }
 
     fn get_dictionary(
@@ -144,38 +406,361 @@ impl Library {
     }
 }
 
-fn construct_dictionaries<P: AsRef<Path>, Q: AsRef<Path>>(
-    user_defined_library_dir: P,
-    builtin_library_dir: Q,
-) -> Vec<Dictionary> {
-    get_dictionary_file_paths(user_defined_library_dir)
-        .iter()
-        .map(|p| (DictionaryOrigin::UserDefined, p))
-        .chain(
-            get_dictionary_file_paths(builtin_library_dir)
-                .iter()
-                .map(|p| (DictionaryOrigin::Builtin, p)),
-        )
-        .map(|(origin, path)| Dictionary::new(path, origin))
-        .filter(|may_dictionary| may_dictionary.is_some())
-        .map(|some_dictionary| some_dictionary.unwrap())
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LibraryError {
+    DictionaryNotFound(DictionaryOrigin, String),
+    // 拡張子がtconciergewでもtconciergesでもない
+    InvalidDictionaryFile(PathBuf),
+    // 有効な語彙が1つもない
+    NoValidVocabulary(PathBuf),
+    Io(PathBuf, String),
+    // 組み込み辞書は編集できない
+    ReadOnlyDictionary(String),
+    // 辞書名として使えない文字列（空の要素や..を含むなど）
+    InvalidDictionaryName(String),
+    DictionaryAlreadyExists(String),
+    // 辞書名と行番号で、語彙の行でない場合も含む
+    EntryNotFound(String, usize),
+    InvalidEntry(ParseDiagnostic),
+}
+
+impl Display for LibraryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DictionaryNotFound(origin, name) => {
+                write!(f, "dictionary {} ({:?}) is not found", name, origin)
+            }
+            Self::InvalidDictionaryFile(path) => {
+                write!(f, "{} is not a dictionary file", path.display())
+            }
+            Self::NoValidVocabulary(path) => {
+                write!(f, "{} has no valid vocabulary", path.display())
+            }
+            Self::Io(path, message) => write!(f, "{}: {}", path.display(), message),
+            Self::ReadOnlyDictionary(name) => write!(f, "dictionary {} is read-only", name),
+            Self::InvalidDictionaryName(name) => {
+                write!(f, "{} is not a valid dictionary name", name)
+            }
+            Self::DictionaryAlreadyExists(name) => {
+                write!(f, "dictionary {} already exists", name)
+            }
+            Self::EntryNotFound(name, line_number) => {
+                write!(
+                    f,
+                    "dictionary {} has no entry at line {}",
+                    name, line_number
+                )
+            }
+            Self::InvalidEntry(diagnostic) => {
+                write!(f, "{} is not a valid entry", diagnostic.text)
+            }
+        }
+    }
+}
+
+impl Error for LibraryError {}
+
+// 取り込む辞書と同じ名前の辞書が既にある場合の扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportConflictResolution {
+    // 「辞書名 (2)」のように番号を付けた名前で取り込む
+    Rename,
+    Overwrite,
+    Skip,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportAction {
+    Imported,
+    Renamed,
+    Overwritten,
+    Skipped,
+}
+
+// 辞書を取り込んだ結果
+// 取り込まなかった場合でも検証結果は返す
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportDictionaryResult {
+    // 取り込んだ（取り込もうとした）ユーザー定義辞書の辞書名
+    name: String,
+    action: ImportAction,
+    valid_vocabulary_count: usize,
+    diagnostics: Vec<ParseDiagnostic>,
+    // 行を無効にはしない綴りの置き換えなどの通知
+    notices: Vec<ParseDiagnostic>,
+}
+
+// 語彙に付けられたタグによる絞り込み
+// includeが空でなければそのいずれかのタグを持つ語彙だけを使い、excludeのいずれかのタグを持つ語彙は使わない
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagFilter {
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+}
+
+impl TagFilter {
+    fn matches(&self, tags: &[String]) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|tag| tags.contains(tag)))
+            && !self.exclude.iter().any(|tag| tags.contains(tag))
+    }
+}
+
+// 辞書に対する1語彙分の編集
+// 行番号は診断結果と同じく1行目から始まるファイル上の行番号
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DictionaryEdit {
+    Add(String),
+    Update(usize, String),
+    Delete(usize),
+}
+
+impl DictionaryEdit {
+    // 書き戻す前に追加・更新する行が語彙として有効かを検証し、編集後のファイルの内容を返す
+    // 更新・削除できるのは語彙の行だけで、ヘッダーや空行・コメントだけの行は対象にできない
+    // 改行コードと末尾の改行の有無は元のファイルに合わせる
+    fn apply(
+        self,
+        dictionary_name: &str,
+        content: &str,
+        spell_normalization: bool,
+    ) -> Result<String, LibraryError> {
+        let entry_not_found =
+            |line_number| LibraryError::EntryNotFound(dictionary_name.to_string(), line_number);
+
+        let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+
+        match self {
+            Self::Add(line) => {
+                lines.push(validate_dictionary_line(&line, spell_normalization)?);
+            }
+            Self::Update(line_number, line) => {
+                let line = validate_dictionary_line(&line, spell_normalization)?;
+                let i = entry_line_index(&lines, line_number)
+                    .ok_or_else(|| entry_not_found(line_number))?;
+                lines[i] = line;
+            }
+            Self::Delete(line_number) => {
+                let i = entry_line_index(&lines, line_number)
+                    .ok_or_else(|| entry_not_found(line_number))?;
+                lines.remove(i);
+            }
+        }
+
+        let line_ending = if content.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+
+        let mut edited_content = lines.join(line_ending);
+        if !edited_content.is_empty() && (content.is_empty() || content.ends_with('\n')) {
+            edited_content.push_str(line_ending);
+        }
+
+        Ok(edited_content)
+    }
+}
+
+// 行番号が語彙の行を指していればそのインデックスを返す
+fn entry_line_index(lines: &[String], line_number: usize) -> Option<usize> {
+    let line_strs: Vec<&str> = lines.iter().map(String::as_str).collect();
+    let (_, body_start) = parse_dictionary_header(&line_strs, &mut vec![]);
+
+    let i = line_number.checked_sub(1)?;
+    let line = lines.get(i)?;
+
+    (i >= body_start && !strip_comment(line).trim().is_empty()).then(|| i)
+}
+
+// 語彙として有効な行であれば書き込む形式に揃えた行を返す
+// 綴りを置き換える設定の場合には置き換えた綴りで書き込む
+// 1行に収まらないものは語彙としてパースできても辞書に書き込めない
+fn validate_dictionary_line(line: &str, spell_normalization: bool) -> Result<String, LibraryError> {
+    let mut normalizations = vec![];
+
+    let (vocabulary_entry, tags) =
+        parse_dictionary_line(line, spell_normalization.then(|| &mut normalizations)).map_err(
+            |(reason, column_range)| {
+                LibraryError::InvalidEntry(ParseDiagnostic::new(1, line, column_range, reason))
+            },
+        )?;
+
+    let mut validated_line = serialize_vocabulary_entry(&vocabulary_entry).ok_or_else(|| {
+        LibraryError::InvalidEntry(ParseDiagnostic::new(
+            1,
+            line,
+            0..line.chars().count(),
+            ParseDiagnosticReason::InvalidVocabulary,
+        ))
+    })?;
+
+    if !tags.is_empty() {
+        let tags: Vec<String> = tags.iter().map(|tag| escape_spell(tag)).collect();
+        validated_line.push(':');
+        validated_line.push_str(&tags.join(","));
+    }
+
+    Ok(validated_line)
+}
+
+// ユーザー定義辞書のライブラリディレクトリにおける辞書名に対応するファイルパス
+// ライブラリディレクトリの外を指す辞書名は受け付けない
+// Windowsのドライブ指定（`C:`）など通常のファイル名でない要素を含むものも受け付けない
+fn dictionary_path_of(
+    library_dir: &Path,
+    dictionary_name: &str,
+    dictionary_type: &DictionaryType,
+) -> Option<PathBuf> {
+    let mut path = library_dir.to_owned();
+
+    for component in dictionary_name.split('/') {
+        if component.contains(':') || component.contains('\\') {
+            return None;
+        }
+
+        let mut components = Path::new(component).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(normal)), None) if normal == component => path.push(normal),
+            _ => return None,
+        }
+    }
+
+    let extension = match dictionary_type {
+        DictionaryType::Word => "tconciergew",
+        DictionaryType::Sentence => "tconcierges",
+    };
+    let mut file_name = path.file_name()?.to_owned();
+    file_name.push(".");
+    file_name.push(extension);
+
+    Some(path.with_file_name(file_name))
+}
+
+// アプリケーション用ディレクトリに保存するライブラリの設定
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LibrarySettings {
+    #[serde(default)]
+    spell_normalization: bool,
+}
+
+// 設定ファイルがない場合や壊れている場合には既定の設定を使う
+fn load_library_settings(settings_file_path: &Path) -> LibrarySettings {
+    read_to_string(settings_file_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn numbered_file_name(file_stem: &str, extension: &str, number: usize) -> String {
+    format!("{} ({}).{}", file_stem, number, extension)
+}
+
+// ユーザー定義辞書と組み込み辞書のライブラリディレクトリ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibraryDirs {
+    user_defined: PathBuf,
+    builtin: PathBuf,
+}
+
+impl LibraryDirs {
+    pub fn user_defined(&self) -> &Path {
+        &self.user_defined
+    }
+}
+
+// 走査して見つけた辞書ファイル
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DictionaryFile {
+    origin: DictionaryOrigin,
+    name: String,
+    path: PathBuf,
+}
+
+// ライブラリディレクトリを走査して辞書ファイルの一覧を得る
+pub fn scan_dictionary_files(library_dirs: &LibraryDirs) -> Vec<DictionaryFile> {
+    let with_name = |origin: DictionaryOrigin, library_dir: &Path| {
+        get_dictionary_file_paths(library_dir)
+            .into_iter()
+            .filter_map(|path| {
+                dictionary_name_of(library_dir, &path).map(|name| DictionaryFile {
+                    origin: origin.clone(),
+                    name,
+                    path,
+                })
+            })
+            .collect::<Vec<DictionaryFile>>()
+    };
+
+    with_name(DictionaryOrigin::UserDefined, &library_dirs.user_defined)
+        .into_iter()
+        .chain(with_name(DictionaryOrigin::Builtin, &library_dirs.builtin))
         .collect()
 }
 
 // ライブラリディレクトリ配下にある拡張子がtconciergewまたはtconciergesのファイルパス一覧
-// 再帰的走査はしない
+// サブディレクトリも再帰的に走査する
+// 走査中に削除されたなどで読めなかったファイルやディレクトリは読み飛ばす
 fn get_dictionary_file_paths<P: AsRef<Path>>(library_dir_path: P) -> Vec<PathBuf> {
-    read_dir(library_dir_path.as_ref(), false)
-        .unwrap()
-        .iter()
-        .filter(|disk_entry| !is_dir(&disk_entry.path).unwrap())
-        .filter(|disk_entry| {
-            disk_entry.path.extension().map_or(false, |extension| {
-                extension == "tconciergew" || extension == "tconcierges"
-            })
-        })
-        .map(|disk_entry| disk_entry.path.clone())
-        .collect()
+    let mut dictionary_file_paths = vec![];
+
+    collect_dictionary_file_paths(
+        library_dir_path.as_ref(),
+        &mut HashSet::new(),
+        &mut dictionary_file_paths,
+    );
+
+    dictionary_file_paths
+}
+
+// シンボリックリンクが循環していても止まるように実体が同じディレクトリは1度だけ走査する
+fn collect_dictionary_file_paths(
+    dir_path: &Path,
+    visited_dir_paths: &mut HashSet<PathBuf>,
+    dictionary_file_paths: &mut Vec<PathBuf>,
+) {
+    let is_first_visit = canonicalize(dir_path).map_or(false, |canonical_dir_path| {
+        visited_dir_paths.insert(canonical_dir_path)
+    });
+    if !is_first_visit {
+        return;
+    }
+
+    let mut paths: Vec<PathBuf> = match read_dir(dir_path) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect(),
+        Err(_) => return,
+    };
+    paths.sort();
+
+    for path in paths {
+        if path.is_dir() {
+            collect_dictionary_file_paths(&path, visited_dir_paths, dictionary_file_paths);
+        } else if path.extension().map_or(false, |extension| {
+            extension == "tconciergew" || extension == "tconcierges"
+        }) {
+            dictionary_file_paths.push(path);
+        }
+    }
+}
+
+// ライブラリディレクトリからの相対パスから拡張子を除いたものを辞書名とする
+// 異なるディレクトリに同じ名前のファイルがあっても区別できるようにするため
+// OSによらず同じ名前になるようにディレクトリの区切りは/にする
+fn dictionary_name_of(library_dir: &Path, path: &Path) -> Option<String> {
+    let relative_path = path.strip_prefix(library_dir).ok()?.with_extension("");
+
+    relative_path
+        .components()
+        .map(|component| component.as_os_str().to_str())
+        .collect::<Option<Vec<&str>>>()
+        .map(|components| components.join("/"))
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -193,29 +778,19 @@ impl CategorizedDictionaryInfos {
 // UI側に見せる辞書情報
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DictionaryInfo {
+    // ライブラリディレクトリからの相対パス（拡張子を除く）で辞書を一意に表す
     name: String,
+    // 辞書の置かれたサブディレクトリのパス（ex. business）で直下にある場合には空
+    category: String,
     dictionary_type: DictionaryType,
     origin: DictionaryOrigin,
+    metadata: DictionaryMetadata,
+    // 語彙に付けられているタグの一覧（重複なし、昇順）
+    tags: Vec<String>,
     valid_vocabulary_count: usize,
-    invalid_line_numbers: Vec<usize>,
-}
-
-impl DictionaryInfo {
-    fn new(
-        name: String,
-        dictionary_type: DictionaryType,
-        origin: DictionaryOrigin,
-        valid_vocabulary_count: usize,
-        invalid_line_numbers: Vec<usize>,
-    ) -> Self {
-        Self {
-            name,
-            dictionary_type,
-            origin,
-            valid_vocabulary_count,
-            invalid_line_numbers,
-        }
-    }
+    diagnostics: Vec<ParseDiagnostic>,
+    // 行を無効にはしない綴りの置き換えなどの通知
+    notices: Vec<ParseDiagnostic>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -232,41 +807,202 @@ pub enum DictionaryOrigin {
     UserDefined,
 }
 
+// 辞書のパース時に無効とされた行についての情報
+// 列の範囲は行頭を0とした文字単位の半開区間
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseDiagnostic {
+    line_number: usize,
+    column_start: usize,
+    column_end: usize,
+    text: String,
+    reason: ParseDiagnosticReason,
+}
+
+impl ParseDiagnostic {
+    fn new(
+        line_number: usize,
+        line: &str,
+        column_range: Range<usize>,
+        reason: ParseDiagnosticReason,
+    ) -> Self {
+        let text = line
+            .chars()
+            .skip(column_range.start)
+            .take(column_range.len())
+            .collect();
+
+        Self {
+            line_number,
+            column_start: column_range.start,
+            column_end: column_range.end,
+            text,
+            reason,
+        }
+    }
+}
+
+// 行が無効とされた理由
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ParseDiagnosticReason {
+    // 語彙と綴りを区切る:がない
+    MissingColon,
+    // エスケープされていない:が2つ以上ある
+    ExtraColon,
+    // 角括弧の中に角括弧がある
+    NestedSquareParentheses,
+    // 対応する開き角括弧がない閉じ角括弧がある
+    UnmatchedClosingSquareParenthesis,
+    // 閉じられていない開き角括弧がある
+    UnclosedSquareParenthesis,
+    // 中身が空の角括弧がある
+    EmptySquareParentheses,
+    // 語彙のまとまりの数と綴りの数が一致しない
+    SpellCountMismatch {
+        view_segment_count: usize,
+        spell_count: usize,
+    },
+    // 綴りに使えない文字が含まれている
+    InvalidSpell {
+        spell: String,
+        invalid_char: char,
+    },
+    // 上記以外の理由で語彙を構築できない
+    InvalidVocabulary,
+    // ヘッダーの終わりを表す区切り行がない
+    UnclosedHeader,
+    // ヘッダーに辞書情報として定義されていない項目がある
+    UnknownHeaderKey {
+        key: String,
+    },
+    // タグの列に空のタグがある
+    InvalidTag {
+        tag: String,
+    },
+    // 綴りに使えない文字を使える文字に置き換えた（行は有効）
+    NormalizedSpell {
+        spell: String,
+        normalized: String,
+    },
+    // タグの列がなくコメントが#で始まる語だけでできているのでタグのつもりで書いた可能性がある（行は有効）
+    TagLikeComment {
+        tags: Vec<String>,
+    },
+}
+
+impl ParseDiagnosticReason {
+    // 行を無効にはせずに知らせるだけのもの
+    fn is_notice(&self) -> bool {
+        matches!(
+            self,
+            Self::NormalizedSpell { .. } | Self::TagLikeComment { .. }
+        )
+    }
+}
+
+// 辞書ファイルの先頭に任意で書けるヘッダーの情報
+// 書かれていない項目は空になる
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DictionaryMetadata {
+    // 表示用の辞書名
+    title: Option<String>,
+    author: Option<String>,
+    description: Option<String>,
+    license: Option<String>,
+    // 入力対象の言語（ex. ja）
+    language: Option<String>,
+    version: Option<String>,
+}
+
 // 内部で使う辞書情報
 pub struct Dictionary {
     name: String,
     dictionary_type: DictionaryType,
     origin: DictionaryOrigin,
     path: PathBuf,
+    metadata: DictionaryMetadata,
     vocabulary_entries: Vec<VocabularyEntry>,
-    invalid_line_numbers: Vec<usize>,
+    // それぞれの語彙に付けられたタグで語彙と同じ順番に並ぶ
+    vocabulary_tags: Vec<Vec<String>>,
+    diagnostics: Vec<ParseDiagnostic>,
+    notices: Vec<ParseDiagnostic>,
+    // 読み込んだ時点でのファイルの更新時刻と内容のハッシュ値
+    modified: Option<SystemTime>,
+    content_hash: u64,
 }
 
 impl Dictionary {
-    fn new<P: AsRef<Path>>(path: P, origin: DictionaryOrigin) -> Option<Self> {
+    // 前回読み込んだ辞書が渡された場合にはファイルが更新されていなければそれを使う
+    // 更新時刻が変わっていても内容が同じならパースし直さない
+    // パースし直したかどうかも返す
+    fn load<P: AsRef<Path>>(
+        path: P,
+        name: String,
+        origin: DictionaryOrigin,
+        previous_dictionary: Option<Self>,
+        spell_normalization: bool,
+    ) -> Option<(Self, bool)> {
+        let modified = path
+            .as_ref()
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok();
+
+        let previous_dictionary = match previous_dictionary {
+            Some(previous_dictionary)
+                if modified.is_some() && previous_dictionary.modified == modified =>
+            {
+                return Some((previous_dictionary, false));
+            }
+            previous_dictionary => previous_dictionary,
+        };
+
         let dictionary_type = match path.as_ref().extension().unwrap().to_str() {
             Some("tconcierges") => DictionaryType::Sentence,
             Some("tconciergew") => DictionaryType::Word,
             _ => unreachable!(),
         };
 
-        let dictionary_name = path.as_ref().file_stem()?;
-
         let mut f = File::open(&path).ok()?;
 
         let mut content = String::new();
         f.read_to_string(&mut content).ok()?;
 
-        let (vocabulary_entries, invalid_line_numbers) = parse_dictionary_content(&content);
+        let content_hash = hash_content(&content);
+
+        if let Some(previous_dictionary) = previous_dictionary {
+            if previous_dictionary.content_hash == content_hash {
+                return Some((
+                    Self {
+                        modified,
+                        ..previous_dictionary
+                    },
+                    false,
+                ));
+            }
+        }
 
-        Some(Self {
-            name: dictionary_name.to_str()?.to_string(),
-            dictionary_type,
-            origin,
-            path: path.as_ref().to_owned(),
-            vocabulary_entries,
-            invalid_line_numbers,
-        })
+        let (metadata, tagged_vocabulary_entries, diagnostics) =
+            parse_dictionary_file(&content, spell_normalization);
+        let (vocabulary_entries, vocabulary_tags) = tagged_vocabulary_entries.into_iter().unzip();
+        let (diagnostics, notices) = split_notices(diagnostics);
+
+        Some((
+            Self {
+                name,
+                dictionary_type,
+                origin,
+                path: path.as_ref().to_owned(),
+                metadata,
+                vocabulary_entries,
+                vocabulary_tags,
+                diagnostics,
+                notices,
+                modified,
+                content_hash,
+            },
+            true,
+        ))
     }
 
     fn name(&self) -> &str {
@@ -277,116 +1013,556 @@ impl Dictionary {
         &self.vocabulary_entries
     }
 
+    // 辞書名から最後の/以降を除いたもの
+    fn category(&self) -> &str {
+        self.name
+            .rsplit_once('/')
+            .map_or("", |(category, _)| category)
+    }
+
     fn construct_dictionary_info(&self) -> DictionaryInfo {
-        DictionaryInfo::new(
-            self.name.clone(),
-            self.dictionary_type.clone(),
-            self.origin.clone(),
-            self.vocabulary_entries.len(),
-            self.invalid_line_numbers.clone(),
-        )
+        DictionaryInfo {
+            name: self.name.clone(),
+            category: self.category().to_string(),
+            dictionary_type: self.dictionary_type.clone(),
+            origin: self.origin.clone(),
+            metadata: self.metadata.clone(),
+            tags: self
+                .vocabulary_tags
+                .iter()
+                .flatten()
+                .cloned()
+                .collect::<BTreeSet<String>>()
+                .into_iter()
+                .collect(),
+            valid_vocabulary_count: self.vocabulary_entries.len(),
+            diagnostics: self.diagnostics.clone(),
+            notices: self.notices.clone(),
+        }
     }
 }
 
+fn hash_content(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+
+    hasher.finish()
+}
+
+// 語彙とその語彙に付けられたタグ
+type TaggedVocabularyEntry = (VocabularyEntry, Vec<String>);
+
+// 行についての診断の理由と行内の範囲
+type LineDiagnostic = (ParseDiagnosticReason, Range<usize>);
+
 // 辞書をパースする
-fn parse_dictionary_content(file_content: &str) -> (Vec<VocabularyEntry>, Vec<usize>) {
-    let mut vocabulary_entries = Vec::<VocabularyEntry>::new();
-    let mut invalid_line_numbers = Vec::<usize>::new();
+fn parse_dictionary_content(
+    file_content: &str,
+    spell_normalization: bool,
+) -> (Vec<VocabularyEntry>, Vec<ParseDiagnostic>) {
+    let (_, tagged_vocabulary_entries, diagnostics) =
+        parse_dictionary_file(file_content, spell_normalization);
+
+    (
+        tagged_vocabulary_entries
+            .into_iter()
+            .map(|(vocabulary_entry, _)| vocabulary_entry)
+            .collect(),
+        diagnostics,
+    )
+}
+
+// ヘッダーを含めた辞書ファイル全体をパースし語彙とそのタグを構築する
+// ヘッダーがない場合には辞書情報は全て空になる
+// 綴りを置き換えた場合にはそのことも診断結果に含める
+fn parse_dictionary_file(
+    file_content: &str,
+    spell_normalization: bool,
+) -> (
+    DictionaryMetadata,
+    Vec<TaggedVocabularyEntry>,
+    Vec<ParseDiagnostic>,
+) {
+    let lines: Vec<&str> = file_content.lines().collect();
+
+    let mut vocabulary_entries = Vec::<TaggedVocabularyEntry>::new();
+    let mut diagnostics = Vec::<ParseDiagnostic>::new();
+
+    let (metadata, body_start) = parse_dictionary_header(&lines, &mut diagnostics);
+
+    for (i, line) in lines.iter().enumerate().skip(body_start) {
+        // 空行とコメントだけの行は語彙として扱わない
+        if strip_comment(line).trim().is_empty() {
+            continue;
+        }
 
-    for (i, line) in file_content.lines().enumerate() {
-        let elements: Vec<String> = split_by_non_escaped(line, ':');
         // 行数は1行目から始まる
         let line_number = i + 1;
 
-        // 有効な行は語彙と綴りの2つを:で区切られている
-        if elements.len() == 2 {
-            let view = elements.get(0).unwrap();
-            let spells_str = elements.get(1).unwrap();
-
-            if let Some((view, view_parts_counts)) = remove_square_parentheses(view) {
-                let spells = split_by_non_escaped(spells_str, ',');
-
-                // spellsの中の2連バックスラッシュを解決する
-                let spells: Vec<String> = spells
-                    .iter()
-                    .map(|spell| convert_two_backslash_to_single(spell))
-                    .collect();
-
-                // 語彙のまとまりの数と綴りのまとまりの数が一致している必要がある
-                if spells.len() == view_parts_counts.len() {
-                    if let Some(spell_strings) = construct_spell_strings(&spells) {
-                        let spells: Vec<VocabularySpellElement> = spell_strings
-                            .iter()
-                            .zip(view_parts_counts)
-                            .map(|(spell, count)| {
-                                if count == 1 {
-                                    VocabularySpellElement::Normal(spell.clone())
-                                } else {
-                                    VocabularySpellElement::Compound((
-                                        spell.clone(),
-                                        NonZeroUsize::new(count).unwrap(),
-                                    ))
-                                }
-                            })
-                            .collect();
-
-                        if let Some(vocabulary_entry) = VocabularyEntry::new(view.clone(), spells) {
-                            vocabulary_entries.push(vocabulary_entry);
-                        } else {
-                            invalid_line_numbers.push(line_number);
-                        }
-                    } else {
-                        invalid_line_numbers.push(line_number);
-                    }
+        let mut normalizations = vec![];
+
+        match parse_dictionary_line(line, spell_normalization.then(|| &mut normalizations)) {
+            Ok(tagged_vocabulary_entry) => {
+                let tag_like_comment = if tagged_vocabulary_entry.1.is_empty() {
+                    find_tag_like_comment(line)
                 } else {
-                    invalid_line_numbers.push(line_number);
-                }
-            } else {
-                invalid_line_numbers.push(line_number);
+                    None
+                };
+
+                vocabulary_entries.push(tagged_vocabulary_entry);
+                diagnostics.extend(normalizations.into_iter().chain(tag_like_comment).map(
+                    |(reason, column_range)| {
+                        ParseDiagnostic::new(line_number, line, column_range, reason)
+                    },
+                ));
             }
+            Err((reason, column_range)) => diagnostics.push(ParseDiagnostic::new(
+                line_number,
+                line,
+                column_range,
+                reason,
+            )),
+        }
+    }
+
+    (metadata, vocabulary_entries, diagnostics)
+}
+
+// 診断結果を無効な行についてのものと行を無効にはしない通知に分ける
+fn split_notices(
+    diagnostics: Vec<ParseDiagnostic>,
+) -> (Vec<ParseDiagnostic>, Vec<ParseDiagnostic>) {
+    diagnostics
+        .into_iter()
+        .partition(|diagnostic| !diagnostic.reason.is_notice())
+}
+
+// 先頭行と次に現れる区切り行で囲まれた部分をヘッダーとしてパースする
+// 語彙が始まる行のインデックスも返す
+fn parse_dictionary_header(
+    lines: &[&str],
+    diagnostics: &mut Vec<ParseDiagnostic>,
+) -> (DictionaryMetadata, usize) {
+    let mut metadata = DictionaryMetadata::default();
+
+    if !lines
+        .first()
+        .map_or(false, |line| is_header_delimiter(line))
+    {
+        return (metadata, 0);
+    }
+
+    let header_end = match lines
+        .iter()
+        .skip(1)
+        .position(|line| is_header_delimiter(line))
+    {
+        Some(position) => position + 1,
+        None => {
+            // 閉じられていない場合には区切り行以外を語彙の行として扱う
+            diagnostics.push(ParseDiagnostic::new(
+                1,
+                lines[0],
+                0..lines[0].chars().count(),
+                ParseDiagnosticReason::UnclosedHeader,
+            ));
+
+            return (metadata, 1);
+        }
+    };
+
+    // 値には#を含められるように#で始まる行だけをコメントとし、値はそのまま使う
+    for (i, line) in lines.iter().enumerate().take(header_end).skip(1) {
+        if line.trim().is_empty() || line.trim_start().starts_with(COMMENT_MARKER) {
+            continue;
+        }
+
+        let line_length = line.chars().count();
+
+        let (key, value) = match line.split_once(':') {
+            Some(key_value) => key_value,
+            None => {
+                diagnostics.push(ParseDiagnostic::new(
+                    i + 1,
+                    line,
+                    0..line_length,
+                    ParseDiagnosticReason::MissingColon,
+                ));
+                continue;
+            }
+        };
+
+        let value = value.trim();
+        let value = if value.is_empty() {
+            None
         } else {
-            invalid_line_numbers.push(line_number);
+            Some(value.to_string())
+        };
+
+        match key.trim() {
+            "title" => metadata.title = value,
+            "author" => metadata.author = value,
+            "description" => metadata.description = value,
+            "license" => metadata.license = value,
+            "language" => metadata.language = value,
+            "version" => metadata.version = value,
+            key => diagnostics.push(ParseDiagnostic::new(
+                i + 1,
+                line,
+                0..key.chars().count(),
+                ParseDiagnosticReason::UnknownHeaderKey {
+                    key: key.to_string(),
+                },
+            )),
         }
     }
 
-    (vocabulary_entries, invalid_line_numbers)
+    (metadata, header_end + 1)
+}
+
+// エスケープされていない#以降をコメントとして取り除く
+fn strip_comment(line: &str) -> &str {
+    find_comment_marker(line).map_or(line, |position| line[..position].trim_end())
+}
+
+// 3つ目の要素として,区切りで並べたタグをパースする
+// 空白だけの場合にはタグなしとし、空のタグは無効とする
+fn parse_tags(tags_element: &SplitElement) -> Result<Vec<String>, LineDiagnostic> {
+    if tags_element.text.trim().is_empty() {
+        return Ok(vec![]);
+    }
+
+    split_by_non_escaped(&tags_element.text, ',')
+        .iter()
+        .map(|tag| {
+            let tag_str = convert_two_backslash_to_single(tag.text.trim());
+
+            if tag_str.is_empty() {
+                Err((
+                    ParseDiagnosticReason::InvalidTag { tag: tag_str },
+                    tags_element.original_range(tag.range.clone()),
+                ))
+            } else {
+                Ok(tag_str)
+            }
+        })
+        .collect()
+}
+
+// `#verb #N3`のように#で始まる語だけでできたコメントをタグとして取り出す
+// タグは3つ目の要素に書くのでコメントに書かれたものは語彙に付けずに知らせるだけにする
+fn find_tag_like_comment(line: &str) -> Option<LineDiagnostic> {
+    let comment_position = find_comment_marker(line)?;
+    let comment = line[comment_position..].trim_end();
+
+    let tags = comment
+        .split_whitespace()
+        .map(|word| {
+            word.strip_prefix(COMMENT_MARKER)
+                .filter(|tag| !tag.is_empty() && !tag.contains(COMMENT_MARKER))
+                .map(str::to_string)
+        })
+        .collect::<Option<Vec<String>>>()?;
+
+    let comment_start = line[..comment_position].chars().count();
+
+    Some((
+        ParseDiagnosticReason::TagLikeComment { tags },
+        comment_start..comment_start + comment.chars().count(),
+    ))
+}
+
+// エスケープされていない最初の#の位置（バイト単位）
+fn find_comment_marker(line: &str) -> Option<usize> {
+    let mut is_prev_escape = false;
+
+    for (i, char) in line.char_indices() {
+        if char == COMMENT_MARKER && !is_prev_escape {
+            return Some(i);
+        }
+
+        is_prev_escape = char == '\\' && !is_prev_escape;
+    }
+
+    None
+}
+
+// BOM付きのファイルでも先頭行をヘッダーの区切りとして扱えるようにする
+fn is_header_delimiter(line: &str) -> bool {
+    line.trim_start_matches('\u{feff}').trim_end() == HEADER_DELIMITER
+}
+
+// 辞書の1行と同じ形式の文字列から語彙を構築する
+pub fn parse_vocabulary_entry(line: &str) -> Result<VocabularyEntry, ParseDiagnostic> {
+    parse_dictionary_line(line, None)
+        .map(|(vocabulary_entry, _)| vocabulary_entry)
+        .map_err(|(reason, column_range)| ParseDiagnostic::new(1, line, column_range, reason))
+}
+
+// 辞書の1行をパースして語彙とタグを構築する
+// 無効な行の場合にはその理由と行内で問題のある範囲を返す
+// normalizationsが渡された場合には綴りを置き換え、置き換えた綴りの範囲をそこに加える
+fn parse_dictionary_line(
+    line: &str,
+    normalizations: Option<&mut Vec<LineDiagnostic>>,
+) -> Result<TaggedVocabularyEntry, LineDiagnostic> {
+    let line = strip_comment(line);
+    let line_length = line.chars().count();
+    let elements = split_by_non_escaped(line, ':');
+
+    // 有効な行は語彙と綴り、任意でタグを:で区切られている
+    match elements.len() {
+        1 => return Err((ParseDiagnosticReason::MissingColon, 0..line_length)),
+        2 | 3 => {}
+        _ => {
+            // 4つ目の要素の直前にあるのが余分な:
+            let extra_colon_position = elements[3].range.start - 1;
+            return Err((
+                ParseDiagnosticReason::ExtraColon,
+                extra_colon_position..line_length,
+            ));
+        }
+    }
+
+    let view_element = &elements[0];
+    let spells_element = &elements[1];
+    let tags = elements
+        .get(2)
+        .map(parse_tags)
+        .transpose()?
+        .unwrap_or_default();
+
+    let (view, view_parts_counts) = remove_square_parentheses(&view_element.text)
+        .map_err(|(reason, range)| (reason, view_element.original_range(range)))?;
+
+    let spells = split_by_non_escaped(&spells_element.text, ',');
+
+    // 語彙のまとまりの数と綴りのまとまりの数が一致している必要がある
+    if spells.len() != view_parts_counts.len() {
+        return Err((
+            ParseDiagnosticReason::SpellCountMismatch {
+                view_segment_count: view_parts_counts.len(),
+                spell_count: spells.len(),
+            },
+            spells_element.range.clone(),
+        ));
+    }
+
+    // spellsの中の2連バックスラッシュを解決する
+    let mut spell_strs: Vec<String> = spells
+        .iter()
+        .map(|spell| convert_two_backslash_to_single(&spell.text))
+        .collect();
+
+    if let Some(normalizations) = normalizations {
+        for (i, spell_str) in spell_strs.iter_mut().enumerate() {
+            let normalized = normalize_spell(spell_str);
+
+            if normalized != *spell_str {
+                normalizations.push((
+                    ParseDiagnosticReason::NormalizedSpell {
+                        spell: spell_str.clone(),
+                        normalized: normalized.clone(),
+                    },
+                    spells_element.original_range(spells[i].range.clone()),
+                ));
+                *spell_str = normalized;
+            }
+        }
+    }
+
+    let spell_strings = construct_spell_strings(&spell_strs).map_err(|(i, invalid_char)| {
+        (
+            ParseDiagnosticReason::InvalidSpell {
+                spell: spell_strs[i].clone(),
+                invalid_char,
+            },
+            spells_element.original_range(spells[i].range.clone()),
+        )
+    })?;
+
+    let spells: Vec<VocabularySpellElement> = spell_strings
+        .into_iter()
+        .zip(view_parts_counts)
+        .map(|(spell, count)| {
+            if count == 1 {
+                VocabularySpellElement::Normal(spell)
+            } else {
+                VocabularySpellElement::Compound((spell, NonZeroUsize::new(count).unwrap()))
+            }
+        })
+        .collect();
+
+    VocabularyEntry::new(view, spells)
+        .map(|vocabulary_entry| (vocabulary_entry, tags))
+        .ok_or((ParseDiagnosticReason::InvalidVocabulary, 0..line_length))
+}
+
+// 語彙群を辞書ファイルの形式に変換する
+// 1つでも辞書の1行として表せない語彙があればNoneを返す
+// 辞書ファイルは1行ずつ編集するので今のところ往復の検証にだけ使う
+#[cfg(test)]
+pub fn serialize_dictionary_content(vocabulary_entries: &[VocabularyEntry]) -> Option<String> {
+    let mut content = String::new();
+
+    for vocabulary_entry in vocabulary_entries {
+        content.push_str(&serialize_vocabulary_entry(vocabulary_entry)?);
+        content.push('\n');
+    }
+
+    Some(content)
+}
+
+// 語彙を辞書の1行と同じ形式の文字列に変換する
+// 改行を含む語彙や綴りのない語彙は1行として表せないのでNoneを返す
+pub fn serialize_vocabulary_entry(vocabulary_entry: &VocabularyEntry) -> Option<String> {
+    let view = vocabulary_entry.view();
+
+    if view.contains(|c| c == '\n' || c == '\r') || vocabulary_entry.spells().is_empty() {
+        return None;
+    }
+
+    let mut view_chars = view.chars();
+    let mut line = String::new();
+
+    for spell in vocabulary_entry.spells() {
+        match spell {
+            VocabularySpellElement::Normal(_) => {
+                push_escaped_view_char(&mut line, view_chars.next()?);
+            }
+            VocabularySpellElement::Compound((_, count)) => {
+                line.push('[');
+                for _ in 0..count.get() {
+                    push_escaped_view_char(&mut line, view_chars.next()?);
+                }
+                line.push(']');
+            }
+        }
+    }
+
+    line.push(':');
+
+    let spells: Vec<String> = vocabulary_entry
+        .spells()
+        .iter()
+        .map(|spell| {
+            let spell: &str = match spell {
+                VocabularySpellElement::Normal(spell) => spell,
+                VocabularySpellElement::Compound((spell, _)) => spell,
+            };
+
+            escape_spell(spell)
+        })
+        .collect();
+    line.push_str(&spells.join(","));
+
+    Some(line)
+}
+
+// 語彙の文字では区切りの:と角括弧とバックスラッシュとコメントの#をエスケープする
+fn push_escaped_view_char(line: &mut String, c: char) {
+    if matches!(c, ':' | '[' | ']' | '\\' | COMMENT_MARKER) {
+        line.push('\\');
+    }
+    line.push(c);
+}
+
+// 綴りでは区切りの:と,とバックスラッシュとコメントの#をエスケープする
+// 綴りの中の角括弧は特別な意味を持たないのでそのままにする
+fn escape_spell(spell: &str) -> String {
+    let mut escaped = String::new();
+
+    for c in spell.chars() {
+        if matches!(c, ':' | ',' | '\\' | COMMENT_MARKER) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
 }
 
 // 文字列のリストから綴り文字列のリストに変換する
-// 綴り文字列として不的確なものがあった場合にはNoneを返す
-fn construct_spell_strings(strs: &[String]) -> Option<Vec<SpellString>> {
+// 綴り文字列として不的確なものがあった場合にはその位置と使えない文字を返す
+fn construct_spell_strings(strs: &[String]) -> Result<Vec<SpellString>, (usize, char)> {
     let mut spell_strings = vec![];
-    for str in strs {
-        if let Some(spell_string) = SpellString::try_from(str.to_string()).ok() {
+    for (i, str) in strs.iter().enumerate() {
+        if let Ok(spell_string) = SpellString::try_from(str.to_string()) {
             spell_strings.push(spell_string);
         } else {
-            return None;
-        }
-    }
+            // SpellStringErrorからは使えない文字を取り出せないので1文字ずつ調べ直す
+            let invalid_char = str
+                .chars()
+                .find(|c| SpellString::try_from(c.to_string()).is_err())
+                .unwrap();
+
+            return Err((i, invalid_char));
+        }
+    }
+
+    Ok(spell_strings)
+}
+
+/// 区切り文字で分割された要素
+/// `range`は分割前の文字列におけるこの要素の範囲（文字単位）
+/// `columns`は`text`のそれぞれの文字が分割前の文字列の何文字目に由来するかを表す
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SplitElement {
+    text: String,
+    range: Range<usize>,
+    columns: Vec<usize>,
+}
+
+impl SplitElement {
+    /// `text`における文字範囲を分割前の文字列における文字範囲に変換する
+    fn original_range(&self, text_range: Range<usize>) -> Range<usize> {
+        let start = self
+            .columns
+            .get(text_range.start)
+            .copied()
+            .unwrap_or(self.range.end);
+
+        let end = if text_range.end <= text_range.start {
+            start
+        } else {
+            self.columns
+                .get(text_range.end - 1)
+                .map_or(self.range.end, |column| column + 1)
+        };
 
-    Some(spell_strings)
+        start..end
+    }
 }
 
 /// それぞれの行をセパレータで分割する
 /// ただしバックスラッシュでエスケープされたセパレータはセパレータ文字そのものとみなす
 /// それらの文字以外につけられたバックスラッシュはそのまま保持する
-fn split_by_non_escaped(line: &str, separator: char) -> Vec<String> {
+fn split_by_non_escaped(line: &str, separator: char) -> Vec<SplitElement> {
     assert_ne!(separator, '\\');
 
-    let mut splitted = Vec::<String>::new();
+    let mut splitted = Vec::<SplitElement>::new();
     let mut element = String::new();
+    let mut columns = Vec::<usize>::new();
+    let mut element_start = 0;
 
     let mut is_prev_escape = false;
 
-    for char in line.chars() {
+    for (i, char) in line.chars().enumerate() {
         if char == separator {
             if is_prev_escape {
                 element.push(char);
+                columns.push(i);
 
                 is_prev_escape = false;
             } else {
-                splitted.push(element.clone());
+                splitted.push(SplitElement {
+                    text: element.clone(),
+                    range: element_start..i,
+                    columns: columns.clone(),
+                });
                 element.clear();
+                columns.clear();
+                element_start = i + 1;
 
                 is_prev_escape = false;
             }
@@ -394,6 +1570,8 @@ fn split_by_non_escaped(line: &str, separator: char) -> Vec<String> {
             if is_prev_escape {
                 element.push(char);
                 element.push(char);
+                columns.push(i - 1);
+                columns.push(i);
 
                 is_prev_escape = false;
             } else {
@@ -402,24 +1580,32 @@ fn split_by_non_escaped(line: &str, separator: char) -> Vec<String> {
         } else {
             if is_prev_escape {
                 element.push('\\');
+                columns.push(i - 1);
             }
 
             element.push(char);
+            columns.push(i);
 
             is_prev_escape = false;
         }
     }
 
-    splitted.push(element);
+    splitted.push(SplitElement {
+        text: element,
+        range: element_start..line.chars().count(),
+        columns,
+    });
 
     splitted
 }
 
 /// 角括弧([])を除去し囲まれた部分をひとまとまりとしたそれぞれに何文字あるかを構築する
-/// バックスラッシュでエスケープされた角括弧・バックスラッシュは角括弧・バックスラッシュそのものとして扱う
-/// ネストされていたり対応が取れていなかったらNoneを返す
+/// バックスラッシュでエスケープされた角括弧・バックスラッシュ・#は角括弧・バックスラッシュ・#そのものとして扱う
+/// ネストされていたり対応が取れていなかったらその理由と問題のある範囲（文字単位）を返す
 /// それ以外のバックスラッシュは特に何もしない
-fn remove_square_parentheses(s: &str) -> Option<(String, Vec<usize>)> {
+fn remove_square_parentheses(
+    s: &str,
+) -> Result<(String, Vec<usize>), (ParseDiagnosticReason, Range<usize>)> {
     // 2段階で構築する
     // 1. 角括弧を除去しながら囲まれた部分の位置(除去後の文字列における始まりと終わりのインデックス)を記録する
     // 2. 囲まれた部分の位置をもとにひとまとまりに何文字あるかを構築する
@@ -429,9 +1615,11 @@ fn remove_square_parentheses(s: &str) -> Option<(String, Vec<usize>)> {
     let mut is_prev_escape = false;
     let mut i = 0;
     let mut start_i: Option<usize> = None;
+    // 開き括弧の除去前の文字列における位置
+    let mut start_column: Option<usize> = None;
 
     // 1.
-    for char in s.chars() {
+    for (column, char) in s.chars().enumerate() {
         if char == '[' {
             if is_prev_escape {
                 string.push(char);
@@ -439,9 +1627,13 @@ fn remove_square_parentheses(s: &str) -> Option<(String, Vec<usize>)> {
                 i += 1;
             } else {
                 if start_i.is_some() {
-                    return None;
+                    return Err((
+                        ParseDiagnosticReason::NestedSquareParentheses,
+                        column..column + 1,
+                    ));
                 }
                 start_i.replace(i);
+                start_column.replace(column);
             }
             is_prev_escape = false;
         } else if char == ']' {
@@ -449,17 +1641,22 @@ fn remove_square_parentheses(s: &str) -> Option<(String, Vec<usize>)> {
                 string.push(char);
 
                 i += 1;
-            } else {
-                if start_i.is_none() {
-                    return None;
-                } else {
-                    // 中に1文字も含まない括弧は許容しない
-                    if *start_i.as_ref().unwrap() == i {
-                        return None;
-                    }
-                    surround_positions.push_back((*start_i.as_ref().unwrap(), i - 1));
-                    start_i.take();
+            } else if let Some(start) = start_i.take() {
+                let start_column = start_column.take().unwrap();
+
+                // 中に1文字も含まない括弧は許容しない
+                if start == i {
+                    return Err((
+                        ParseDiagnosticReason::EmptySquareParentheses,
+                        start_column..column + 1,
+                    ));
                 }
+                surround_positions.push_back((start, i - 1));
+            } else {
+                return Err((
+                    ParseDiagnosticReason::UnmatchedClosingSquareParenthesis,
+                    column..column + 1,
+                ));
             }
 
             is_prev_escape = false;
@@ -473,7 +1670,8 @@ fn remove_square_parentheses(s: &str) -> Option<(String, Vec<usize>)> {
                 is_prev_escape = true;
             }
         } else {
-            if is_prev_escape {
+            // エスケープされた#は#そのものとして扱う
+            if is_prev_escape && char != COMMENT_MARKER {
                 string.push('\\');
                 i += 1;
             }
@@ -485,9 +1683,12 @@ fn remove_square_parentheses(s: &str) -> Option<(String, Vec<usize>)> {
         }
     }
 
-    // 最後になっても対応する括弧がないならNone
-    if start_i.is_some() {
-        return None;
+    // 最後になっても対応する括弧がないならエラー
+    if let Some(start_column) = start_column {
+        return Err((
+            ParseDiagnosticReason::UnclosedSquareParenthesis,
+            start_column..s.chars().count(),
+        ));
     }
 
     // 2.
@@ -512,10 +1713,11 @@ fn remove_square_parentheses(s: &str) -> Option<(String, Vec<usize>)> {
         }
     });
 
-    Some((string, character_counts))
+    Ok((string, character_counts))
 }
 
 /// 2回連続でバックスラッシュが出てきたらそれをひとつにする
+/// エスケープされた#も#そのものにする
 fn convert_two_backslash_to_single(s: &str) -> String {
     let mut string = String::new();
 
@@ -529,7 +1731,7 @@ fn convert_two_backslash_to_single(s: &str) -> String {
                 is_prev_escape = true;
             }
         } else {
-            if is_prev_escape {
+            if is_prev_escape && char != COMMENT_MARKER {
                 string.push('\\');
             }
 
@@ -548,27 +1750,216 @@ fn convert_two_backslash_to_single(s: &str) -> String {
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::fs::write;
     use std::num::NonZeroUsize;
     use typing_engine::{VocabularyEntry, VocabularySpellElement};
 
+    #[test]
+    fn dictionary_name_of_1() {
+        let library_dir = Path::new("library");
+
+        assert_eq!(
+            dictionary_name_of(library_dir, &library_dir.join("四字熟語.tconciergew")),
+            Some("四字熟語".to_string())
+        );
+        assert_eq!(
+            dictionary_name_of(
+                library_dir,
+                &library_dir.join("business").join("IT用語.tconciergew")
+            ),
+            Some("business/IT用語".to_string())
+        );
+        assert_eq!(
+            dictionary_name_of(library_dir, Path::new("other/IT用語.tconciergew")),
+            None
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn get_dictionary_file_paths_1() {
+        let library_dir = std::env::temp_dir().join(format!(
+            "tconcierge_get_dictionary_file_paths_1_{}",
+            std::process::id()
+        ));
+        let business_dir = library_dir.join("business");
+        create_dir_all(&business_dir).unwrap();
+
+        write(library_dir.join("四字熟語.tconciergew"), "").unwrap();
+        write(business_dir.join("IT用語.tconciergew"), "").unwrap();
+        write(business_dir.join("memo.txt"), "").unwrap();
+        // 親ディレクトリを指すシンボリックリンクで循環させる
+        std::os::unix::fs::symlink(&library_dir, business_dir.join("loop")).unwrap();
+
+        let paths = get_dictionary_file_paths(&library_dir);
+
+        std::fs::remove_dir_all(&library_dir).unwrap();
+
+        assert_eq!(
+            paths,
+            vec![
+                business_dir.join("IT用語.tconciergew"),
+                library_dir.join("四字熟語.tconciergew"),
+            ]
+        );
+        assert_eq!(
+            get_dictionary_file_paths(&library_dir),
+            Vec::<PathBuf>::new()
+        );
+    }
+
+    #[test]
+    fn dictionary_path_of_1() {
+        let library_dir = Path::new("library");
+
+        assert_eq!(
+            dictionary_path_of(library_dir, "business/IT用語", &DictionaryType::Word),
+            Some(library_dir.join("business").join("IT用語.tconciergew"))
+        );
+        assert_eq!(
+            dictionary_path_of(library_dir, "名言.v2", &DictionaryType::Sentence),
+            Some(library_dir.join("名言.v2.tconcierges"))
+        );
+        assert_eq!(
+            dictionary_path_of(library_dir, "../IT用語", &DictionaryType::Word),
+            None
+        );
+        assert_eq!(
+            dictionary_path_of(library_dir, "business/", &DictionaryType::Word),
+            None
+        );
+        assert_eq!(
+            dictionary_path_of(library_dir, "./IT用語", &DictionaryType::Word),
+            None
+        );
+        assert_eq!(
+            dictionary_path_of(library_dir, "C:", &DictionaryType::Word),
+            None
+        );
+        assert_eq!(
+            dictionary_path_of(library_dir, "C:IT用語", &DictionaryType::Word),
+            None
+        );
+        assert_eq!(
+            dictionary_path_of(library_dir, "business/C:IT用語", &DictionaryType::Word),
+            None
+        );
+    }
+
+    #[test]
+    fn apply_dictionary_edit_1() {
+        let content = "犬:いぬ\n猫\n";
+
+        let content = DictionaryEdit::Add("鳥:とり".to_string())
+            .apply("動物", content, false)
+            .unwrap();
+        let content = DictionaryEdit::Update(2, "猫:ねこ".to_string())
+            .apply("動物", &content, false)
+            .unwrap();
+        let content = DictionaryEdit::Delete(1)
+            .apply("動物", &content, false)
+            .unwrap();
+
+        assert_eq!(content, "猫:ねこ\n鳥:とり\n");
+
+        assert_eq!(
+            DictionaryEdit::Delete(3).apply("動物", &content, false),
+            Err(LibraryError::EntryNotFound("動物".to_string(), 3))
+        );
+        assert!(matches!(
+            DictionaryEdit::Add("[猫:ねこ".to_string()).apply("動物", &content, false),
+            Err(LibraryError::InvalidEntry(..))
+        ));
+        assert!(matches!(
+            DictionaryEdit::Add("犬:いぬ\n猫:ねこ".to_string()).apply("動物", &content, false),
+            Err(LibraryError::InvalidEntry(..))
+        ));
+
+        assert_eq!(
+            DictionaryEdit::Add("ネコ:ネ,コ".to_string()).apply("動物", "", true),
+            Ok("ネコ:ね,こ\n".to_string())
+        );
+    }
+
+    #[test]
+    fn apply_dictionary_edit_2() {
+        let content = "---\r\ntitle: 動物\r\n---\r\n# 哺乳類\r\n\r\n犬:いぬ # 柴犬\r\n猫:ねこ";
+
+        for line_number in 1..=5 {
+            assert_eq!(
+                DictionaryEdit::Delete(line_number).apply("動物", content, false),
+                Err(LibraryError::EntryNotFound("動物".to_string(), line_number))
+            );
+            assert_eq!(
+                DictionaryEdit::Update(line_number, "鳥:とり".to_string())
+                    .apply("動物", content, false),
+                Err(LibraryError::EntryNotFound("動物".to_string(), line_number))
+            );
+        }
+
+        assert_eq!(
+            DictionaryEdit::Update(6, "鳥:とり".to_string()).apply("動物", content, false),
+            Ok("---\r\ntitle: 動物\r\n---\r\n# 哺乳類\r\n\r\n鳥:とり\r\n猫:ねこ".to_string())
+        );
+        assert_eq!(
+            DictionaryEdit::Delete(7).apply("動物", content, false),
+            Ok("---\r\ntitle: 動物\r\n---\r\n# 哺乳類\r\n\r\n犬:いぬ # 柴犬".to_string())
+        );
+    }
+
+    #[test]
+    fn numbered_file_name_1() {
+        assert_eq!(
+            numbered_file_name("IT用語", "tconciergew", 2),
+            "IT用語 (2).tconciergew"
+        );
+    }
+
     #[test]
     fn split_by_non_escaped_1() {
         let v = split_by_non_escaped(r"hoge\\\::", ':');
-        assert_eq!(v, vec![String::from(r"hoge\\:"), String::from("")]);
+        assert_eq!(
+            v,
+            vec![
+                SplitElement {
+                    text: String::from(r"hoge\\:"),
+                    range: 0..8,
+                    columns: vec![0, 1, 2, 3, 4, 5, 7],
+                },
+                SplitElement {
+                    text: String::from(""),
+                    range: 9..9,
+                    columns: vec![],
+                }
+            ]
+        );
     }
 
     #[test]
     fn split_by_non_escaped_2() {
-        let v = split_by_non_escaped(r"hoge:fuga", ':');
-        assert_eq!(v, vec![String::from(r"hoge"), String::from("fuga")]);
+        let v: Vec<(String, Range<usize>)> = split_by_non_escaped(r"hoge:fuga", ':')
+            .into_iter()
+            .map(|element| (element.text, element.range))
+            .collect();
+        assert_eq!(
+            v,
+            vec![(String::from(r"hoge"), 0..4), (String::from("fuga"), 5..9)]
+        );
     }
 
     #[test]
     fn split_by_non_escaped_3() {
-        let v = split_by_non_escaped(r"::", ':');
+        let v: Vec<(String, Range<usize>)> = split_by_non_escaped(r"::", ':')
+            .into_iter()
+            .map(|element| (element.text, element.range))
+            .collect();
         assert_eq!(
             v,
-            vec![String::from(""), String::from(""), String::from("")]
+            vec![
+                (String::from(""), 0..0),
+                (String::from(""), 1..1),
+                (String::from(""), 2..2)
+            ]
         );
     }
 
@@ -576,23 +1967,43 @@ mod test {
     fn remove_square_parentheses_1() {
         assert_eq!(
             remove_square_parentheses(r"a[123\]]b[c]"),
-            Some(("a123]bc".to_string(), vec![1, 4, 1, 1]))
+            Ok(("a123]bc".to_string(), vec![1, 4, 1, 1]))
         );
     }
 
     #[test]
     fn remove_square_parentheses_2() {
-        assert_eq!(remove_square_parentheses(r"[[]]"), None);
+        assert_eq!(
+            remove_square_parentheses(r"[[]]"),
+            Err((ParseDiagnosticReason::NestedSquareParentheses, 1..2))
+        );
     }
 
     #[test]
     fn remove_square_parentheses_3() {
-        assert_eq!(remove_square_parentheses(r"a[bdf\["), None);
+        assert_eq!(
+            remove_square_parentheses(r"a[bdf\["),
+            Err((ParseDiagnosticReason::UnclosedSquareParenthesis, 1..7))
+        );
     }
 
     #[test]
     fn remove_square_parentheses_4() {
-        assert_eq!(remove_square_parentheses(r"[]"), None);
+        assert_eq!(
+            remove_square_parentheses(r"[]"),
+            Err((ParseDiagnosticReason::EmptySquareParentheses, 0..2))
+        );
+    }
+
+    #[test]
+    fn remove_square_parentheses_5() {
+        assert_eq!(
+            remove_square_parentheses(r"ab]"),
+            Err((
+                ParseDiagnosticReason::UnmatchedClosingSquareParenthesis,
+                2..3
+            ))
+        );
     }
 
     #[test]
@@ -617,8 +2028,8 @@ mod test {
 
     #[test]
     fn parse_dictionary_1() {
-        let (ve, iln) =
-            parse_dictionary_content("頑張る:がん,ば,る\n頑張る:がんば,る\n[百舌鳥]:もず");
+        let (ve, diagnostics) =
+            parse_dictionary_content("頑張る:がん,ば,る\n頑張る:がんば,る\n[百舌鳥]:もず", false);
 
         assert_eq!(
             ve,
@@ -643,13 +2054,27 @@ mod test {
             ]
         );
 
-        assert_eq!(iln, vec![2]);
+        assert_eq!(
+            diagnostics,
+            vec![ParseDiagnostic {
+                line_number: 2,
+                column_start: 4,
+                column_end: 9,
+                text: "がんば,る".to_string(),
+                reason: ParseDiagnosticReason::SpellCountMismatch {
+                    view_segment_count: 3,
+                    spell_count: 2
+                },
+            }]
+        );
     }
 
     #[test]
     fn parse_dictionary_2() {
-        let (ve, iln) =
-            parse_dictionary_content("[昨日]の敵は[今日]の友:きのう,の,てき,は,きょう,の,とも");
+        let (ve, diagnostics) = parse_dictionary_content(
+            "[昨日]の敵は[今日]の友:きのう,の,てき,は,きょう,の,とも",
+            false,
+        );
 
         assert_eq!(
             ve,
@@ -674,12 +2099,12 @@ mod test {
             .unwrap(),]
         );
 
-        assert_eq!(iln, vec![] as Vec<usize>);
+        assert_eq!(diagnostics, vec![] as Vec<ParseDiagnostic>);
     }
 
     #[test]
     fn parse_dictionary_3() {
-        let (ve, iln) = parse_dictionary_content(r"\\\::\\,\:");
+        let (ve, diagnostics) = parse_dictionary_content(r"\\\::\\,\:", false);
 
         assert_eq!(
             ve,
@@ -693,14 +2118,15 @@ mod test {
             .unwrap(),]
         );
 
-        assert_eq!(iln, vec![] as Vec<usize>);
+        assert_eq!(diagnostics, vec![] as Vec<ParseDiagnostic>);
     }
 
     #[test]
     fn parse_dictionary_4() {
-        let (ve, iln) = parse_dictionary_content(
+        let (ve, diagnostics) = parse_dictionary_content(
             r"[\[]12:[,1,2
             [[]12:[,1,2",
+            false,
         );
 
         assert_eq!(
@@ -716,6 +2142,427 @@ mod test {
             .unwrap(),]
         );
 
-        assert_eq!(iln, vec![2]);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line_number, 2);
+        assert_eq!(
+            diagnostics[0].reason,
+            ParseDiagnosticReason::NestedSquareParentheses
+        );
+        assert_eq!(diagnostics[0].text, "[");
+    }
+
+    #[test]
+    fn parse_dictionary_5() {
+        let (ve, diagnostics) = parse_dictionary_content(
+            "頑張る\n頑張る:がん:ば,る:動詞\nカタカナ:カ,タ,カ,ナ\na\\:b]:え,ー,び",
+            false,
+        );
+
+        assert_eq!(ve, vec![]);
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                ParseDiagnostic {
+                    line_number: 1,
+                    column_start: 0,
+                    column_end: 3,
+                    text: "頑張る".to_string(),
+                    reason: ParseDiagnosticReason::MissingColon,
+                },
+                ParseDiagnostic {
+                    line_number: 2,
+                    column_start: 10,
+                    column_end: 13,
+                    text: ":動詞".to_string(),
+                    reason: ParseDiagnosticReason::ExtraColon,
+                },
+                ParseDiagnostic {
+                    line_number: 3,
+                    column_start: 5,
+                    column_end: 6,
+                    text: "カ".to_string(),
+                    reason: ParseDiagnosticReason::InvalidSpell {
+                        spell: "カ".to_string(),
+                        invalid_char: 'カ',
+                    },
+                },
+                ParseDiagnostic {
+                    line_number: 4,
+                    column_start: 4,
+                    column_end: 5,
+                    text: "]".to_string(),
+                    reason: ParseDiagnosticReason::UnmatchedClosingSquareParenthesis,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn serialize_vocabulary_entry_1() {
+        let vocabulary_entry = VocabularyEntry::new(
+            "七夕[:]\\".to_string(),
+            vec![
+                VocabularySpellElement::Compound((
+                    "たなばた".to_string().try_into().unwrap(),
+                    NonZeroUsize::new(2).unwrap(),
+                )),
+                VocabularySpellElement::Normal("[".to_string().try_into().unwrap()),
+                VocabularySpellElement::Normal(":".to_string().try_into().unwrap()),
+                VocabularySpellElement::Normal(",".to_string().try_into().unwrap()),
+                VocabularySpellElement::Normal("\\".to_string().try_into().unwrap()),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            serialize_vocabulary_entry(&vocabulary_entry),
+            Some(r"[七夕]\[\:\]\\:たなばた,[,\:,\,,\\".to_string())
+        );
+    }
+
+    #[test]
+    fn serialize_vocabulary_entry_2() {
+        let vocabulary_entry = VocabularyEntry::new(
+            "犬\n".to_string(),
+            vec![
+                VocabularySpellElement::Normal("いぬ".to_string().try_into().unwrap()),
+                VocabularySpellElement::Normal("".to_string().try_into().unwrap()),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(serialize_vocabulary_entry(&vocabulary_entry), None);
+        assert_eq!(
+            serialize_vocabulary_entry(&VocabularyEntry::new("".to_string(), vec![]).unwrap()),
+            None
+        );
+    }
+
+    // 特別な意味を持つ文字を多く含む語彙を乱数で生成してパースすると元に戻ることを確かめる
+    #[test]
+    fn serialize_and_parse_dictionary_1() {
+        use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+        const VIEW_CHARS: [char; 10] = ['a', '犬', 'あ', ' ', ':', ',', '[', ']', '\\', '#'];
+        const SPELL_CHARS: [char; 9] = ['a', 'い', ' ', ':', ',', '[', ']', '\\', '#'];
+
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..100 {
+            let vocabulary_entries: Vec<VocabularyEntry> = (0..rng.gen_range(1..5))
+                .map(|_| {
+                    let mut view = String::new();
+
+                    let spells = (0..rng.gen_range(1..5))
+                        .map(|_| {
+                            let count = rng.gen_range(1..4);
+                            (0..count)
+                                .for_each(|_| view.push(*VIEW_CHARS.choose(&mut rng).unwrap()));
+
+                            let spell: SpellString = (0..rng.gen_range(0..4))
+                                .map(|_| *SPELL_CHARS.choose(&mut rng).unwrap())
+                                .collect::<String>()
+                                .try_into()
+                                .unwrap();
+
+                            if count == 1 {
+                                VocabularySpellElement::Normal(spell)
+                            } else {
+                                VocabularySpellElement::Compound((
+                                    spell,
+                                    NonZeroUsize::new(count).unwrap(),
+                                ))
+                            }
+                        })
+                        .collect();
+
+                    VocabularyEntry::new(view, spells).unwrap()
+                })
+                .collect();
+
+            let content = serialize_dictionary_content(&vocabulary_entries).unwrap();
+            let (parsed_vocabulary_entries, diagnostics) =
+                parse_dictionary_content(&content, false);
+
+            assert_eq!(parsed_vocabulary_entries, vocabulary_entries, "{}", content);
+            assert_eq!(diagnostics, vec![]);
+        }
+    }
+
+    #[test]
+    fn parse_dictionary_header_1() {
+        let (metadata, ve, diagnostics) = parse_dictionary_file(
+            "---\ntitle: 動物\nauthor: \nlanguage: ja\nversion: 1.0\nlicence: CC0\n---\n犬:いぬ",
+            false,
+        );
+
+        assert_eq!(
+            metadata,
+            DictionaryMetadata {
+                title: Some("動物".to_string()),
+                language: Some("ja".to_string()),
+                version: Some("1.0".to_string()),
+                ..DictionaryMetadata::default()
+            }
+        );
+        assert_eq!(
+            ve,
+            vec![(parse_vocabulary_entry("犬:いぬ").unwrap(), vec![])]
+        );
+        assert_eq!(
+            diagnostics,
+            vec![ParseDiagnostic {
+                line_number: 6,
+                column_start: 0,
+                column_end: 7,
+                text: "licence".to_string(),
+                reason: ParseDiagnosticReason::UnknownHeaderKey {
+                    key: "licence".to_string()
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_dictionary_header_2() {
+        let (metadata, ve, diagnostics) = parse_dictionary_file("---\n犬:いぬ", false);
+
+        assert_eq!(metadata, DictionaryMetadata::default());
+        assert_eq!(
+            ve,
+            vec![(parse_vocabulary_entry("犬:いぬ").unwrap(), vec![])]
+        );
+        assert_eq!(
+            diagnostics,
+            vec![ParseDiagnostic {
+                line_number: 1,
+                column_start: 0,
+                column_end: 3,
+                text: "---".to_string(),
+                reason: ParseDiagnosticReason::UnclosedHeader,
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_dictionary_header_3() {
+        let (metadata, _, diagnostics) = parse_dictionary_file(
+            "---\n# 辞書情報\ntitle: C# 入門\ndescription: \\#から行末はコメント\n---\n",
+            false,
+        );
+
+        assert_eq!(
+            metadata,
+            DictionaryMetadata {
+                title: Some("C# 入門".to_string()),
+                description: Some("\\#から行末はコメント".to_string()),
+                ..DictionaryMetadata::default()
+            }
+        );
+        assert_eq!(diagnostics, vec![]);
+    }
+
+    #[test]
+    fn parse_tags_1() {
+        let tags_of = |s: &str| parse_tags(&split_by_non_escaped(s, ':')[1]);
+
+        assert_eq!(tags_of("a:"), Ok(vec![]));
+        assert_eq!(tags_of("a:  "), Ok(vec![]));
+        assert_eq!(
+            tags_of("a:verb, N3"),
+            Ok(vec!["verb".to_string(), "N3".to_string()])
+        );
+        assert_eq!(
+            tags_of(r"a:C\#,\,\:"),
+            Ok(vec!["C#".to_string(), ",:".to_string()])
+        );
+        assert_eq!(
+            tags_of("a:verb,,N3"),
+            Err((
+                ParseDiagnosticReason::InvalidTag {
+                    tag: "".to_string()
+                },
+                7..7
+            ))
+        );
+    }
+
+    #[test]
+    fn tag_filter_1() {
+        let tags = vec!["verb".to_string(), "N3".to_string()];
+
+        assert!(TagFilter::default().matches(&tags));
+        assert!(TagFilter {
+            include: vec!["noun".to_string(), "verb".to_string()],
+            exclude: vec![],
+        }
+        .matches(&tags));
+        assert!(!TagFilter {
+            include: vec!["noun".to_string()],
+            exclude: vec![],
+        }
+        .matches(&tags));
+        assert!(!TagFilter {
+            include: vec![],
+            exclude: vec!["N3".to_string()],
+        }
+        .matches(&tags));
+        assert!(TagFilter {
+            include: vec![],
+            exclude: vec!["N3".to_string()],
+        }
+        .matches(&[]));
+    }
+
+    #[test]
+    fn parse_dictionary_comment_1() {
+        let (ve, diagnostics) =
+            parse_dictionary_content("# 動物\n\n犬:いぬ # 哺乳類\n  \n\\#1:\\#,1\n猫", false);
+
+        assert_eq!(
+            ve,
+            vec![
+                parse_vocabulary_entry("犬:いぬ").unwrap(),
+                VocabularyEntry::new(
+                    "#1".to_string(),
+                    vec![
+                        VocabularySpellElement::Normal("#".to_string().try_into().unwrap()),
+                        VocabularySpellElement::Normal("1".to_string().try_into().unwrap()),
+                    ]
+                )
+                .unwrap(),
+            ]
+        );
+        assert_eq!(
+            diagnostics,
+            vec![ParseDiagnostic {
+                line_number: 6,
+                column_start: 0,
+                column_end: 1,
+                text: "猫".to_string(),
+                reason: ParseDiagnosticReason::MissingColon,
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_dictionary_comment_2() {
+        let (ve, diagnostics) =
+            parse_dictionary_content("犬:いぬ #TODO fix\n猫:ねこ #メモ\n鳥:とり #", false);
+
+        assert_eq!(
+            ve,
+            vec![
+                parse_vocabulary_entry("犬:いぬ").unwrap(),
+                parse_vocabulary_entry("猫:ねこ").unwrap(),
+                parse_vocabulary_entry("鳥:とり").unwrap(),
+            ]
+        );
+        // #で始まる語だけのコメントはタグのつもりで書いた可能性があるので知らせる
+        assert_eq!(
+            diagnostics,
+            vec![ParseDiagnostic {
+                line_number: 2,
+                column_start: 5,
+                column_end: 8,
+                text: "#メモ".to_string(),
+                reason: ParseDiagnosticReason::TagLikeComment {
+                    tags: vec!["メモ".to_string()]
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_dictionary_tag_like_comment_1() {
+        let (_, ve, diagnostics) = parse_dictionary_file(
+            "頑張る:がん,ば,る #verb #N3\n走る:はし,る:verb #五段\n犬:いぬ # #noun\n猫:ねこ #noun#N5",
+            false,
+        );
+
+        // コメントに書いたタグは語彙に付けない
+        assert!(ve.iter().all(|(_, tags)| tags.len() <= 1));
+        assert_eq!(
+            diagnostics,
+            vec![ParseDiagnostic {
+                line_number: 1,
+                column_start: 11,
+                column_end: 20,
+                text: "#verb #N3".to_string(),
+                reason: ParseDiagnosticReason::TagLikeComment {
+                    tags: vec!["verb".to_string(), "N3".to_string()]
+                },
+            }]
+        );
+        assert!(diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.reason.is_notice()));
+    }
+
+    #[test]
+    fn parse_dictionary_tag_1() {
+        let (_, ve, diagnostics) = parse_dictionary_file(
+            "頑張る:がん,ば,る:verb,N3\n犬:いぬ:noun # #メモ\n猫:ねこ:noun,\n鳥:とり:",
+            false,
+        );
+
+        assert_eq!(
+            ve,
+            vec![
+                (
+                    parse_vocabulary_entry("頑張る:がん,ば,る").unwrap(),
+                    vec!["verb".to_string(), "N3".to_string()]
+                ),
+                (
+                    parse_vocabulary_entry("犬:いぬ").unwrap(),
+                    vec!["noun".to_string()]
+                ),
+                (parse_vocabulary_entry("鳥:とり").unwrap(), vec![]),
+            ]
+        );
+        assert_eq!(
+            diagnostics,
+            vec![ParseDiagnostic {
+                line_number: 3,
+                column_start: 10,
+                column_end: 10,
+                text: "".to_string(),
+                reason: ParseDiagnosticReason::InvalidTag {
+                    tag: "".to_string()
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn parse_dictionary_normalization_1() {
+        let (ve, diagnostics) =
+            parse_dictionary_content("タイピング:タ,イ,ピ,ン,グ\n犬:いぬ", true);
+
+        assert_eq!(
+            ve,
+            vec![
+                parse_vocabulary_entry("タイピング:た,い,ぴ,ん,ぐ").unwrap(),
+                parse_vocabulary_entry("犬:いぬ").unwrap(),
+            ]
+        );
+        assert_eq!(diagnostics.len(), 5);
+        assert_eq!(
+            diagnostics[0],
+            ParseDiagnostic {
+                line_number: 1,
+                column_start: 6,
+                column_end: 7,
+                text: "タ".to_string(),
+                reason: ParseDiagnosticReason::NormalizedSpell {
+                    spell: "タ".to_string(),
+                    normalized: "た".to_string(),
+                },
+            }
+        );
+
+        let (ve, _) = parse_dictionary_content("タイピング:タ,イ,ピ,ン,グ", false);
+        assert_eq!(ve, vec![]);
     }
 }
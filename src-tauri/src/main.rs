@@ -3,126 +3,875 @@
     windows_subsystem = "windows"
 )]
 
-use std::{fs::create_dir_all, num::NonZeroUsize, sync::Mutex};
+use std::{
+    cell::RefCell,
+    fs::create_dir_all,
+    path::PathBuf,
+    rc::Rc,
+    sync::{mpsc::channel, Mutex},
+    thread,
+    time::Duration,
+};
+
+use notify::{recommended_watcher, RecursiveMode, Watcher};
 
 use serde::{Deserialize, Serialize};
-use tauri::{generate_handler, Manager, State};
-use typing_engine::{
-    LapRequest, QueryRequest, TypingEngine, TypingEngineError, VocabularyOrder,
-    VocabularyQuantifier, VocabularySeparator,
-};
+use tauri::{generate_handler, AppHandle, Manager, State};
+use typing_engine::{DisplayInfo, QueryRequest, VocabularySeparator};
 
-use library::{CategorizedDictionaryInfos, DictionaryOrigin, DictionaryType, Library};
+use export::{write_rows, ExportFormat, ExportRow};
+use game_session::{GameSession, GameState};
+use ghost::{Ghost, GhostPosition};
 
+use history::{History, HistoryEntry, HistoryFilter, HistoryRecord};
+use key_miss::{KeyMissHeatMap, KeyMissStatistics};
+use library::{
+    scan_dictionary_files, CategorizedDictionaryInfos, DictionaryEdit, DictionaryInfo,
+    DictionaryOrigin, DictionaryType, ImportConflictResolution, ImportDictionaryResult, Library,
+};
+use query_request::{QueryRequestError, QueryRequestFromUI};
+use reading_split::{
+    split_reading, ReadingSplitResult, ReadingTable, MAX_READING_LENGTH, MAX_VIEW_LENGTH,
+};
+use replay::{Replay, ReplayFrame, Replays};
+use review::construct_weakness_scores;
+use vocabulary_order::{construct_recording_order, VocabularyPicker};
+use vocabulary_tracker::VocabularyTracker;
+
+mod atomic_file;
 mod display_info;
+mod error;
+mod export;
+mod game_session;
+mod ghost;
+mod history;
+mod kana;
+mod key_miss;
 mod library;
+mod query_request;
+mod reading_split;
+mod replay;
 mod result;
+mod review;
+mod scoring;
+mod vocabulary_order;
+mod vocabulary_tracker;
 
 use crate::display_info::DisplayInformation;
-use crate::result::TypingResult;
+use crate::error::{ToUIError, ToUIErrorKind};
+use crate::result::{SaveWarning, TypingResult};
+
+// エディタの保存では一時ファイルの作成や置き換えでイベントが続けて起きるので
+// 最後のイベントからこの時間だけ待ってまとめて読み込み直す
+const DICTIONARY_WATCH_DEBOUNCE: Duration = Duration::from_millis(200);
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct ToUIError {}
-
-impl From<TypingEngineError> for ToUIError {
-    fn from(_: TypingEngineError) -> Self {
-        Self {}
-    }
+struct KeyStrokeInfo {
+    key: String,
+    // UI側の経過時間は一時停止していた時間を含むので記録や判定には使わない
+    elapsed_time: usize,
 }
 
+// キーごとのミスタイプの集計対象
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct QueryRequestFromUI {
-    dictionary_type: DictionaryType,
-    used_dictionaries: Vec<(DictionaryOrigin, String)>,
-    key_stroke_count_threshold: Option<NonZeroUsize>,
+#[serde(tag = "type", rename_all = "camelCase")]
+enum KeyMissScope {
+    // 現在（または直前）のタイピング
+    CurrentSession,
+    // 条件に合う保存済みの履歴全体
+    History { filter: HistoryFilter },
 }
 
+// 結果の書き出し対象
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct KeyStrokeInfo {
-    key: String,
-    elapsed_time: usize,
+#[serde(tag = "type", rename_all = "camelCase")]
+enum ExportScope {
+    // 直前のタイピングの結果
+    CurrentResult,
+    // 条件に合う保存済みの履歴
+    History { filter: HistoryFilter },
 }
 
 #[tauri::command]
-fn get_dictionary_infos(library: State<Mutex<Library>>) -> CategorizedDictionaryInfos {
+fn get_dictionary_infos(
+    library: State<Mutex<Library>>,
+) -> Result<CategorizedDictionaryInfos, ToUIError> {
     let mut locked_library = library.lock().unwrap();
 
     locked_library.reload_dictionaries();
-    locked_library.dictionary_infos()
+    Ok(locked_library.dictionary_infos())
+}
+
+// 綴りのカタカナなどを綴りに使える文字に置き換えて読み込むかどうかを切り替える
+#[tauri::command]
+fn set_spell_normalization(
+    spell_normalization: bool,
+    app: AppHandle,
+    library: State<Mutex<Library>>,
+) -> Result<CategorizedDictionaryInfos, ToUIError> {
+    let mut locked_library = library.lock().unwrap();
+
+    if locked_library.set_spell_normalization(spell_normalization)? {
+        emit_dictionaries_changed(&app, &locked_library)?;
+    }
+
+    Ok(locked_library.dictionary_infos())
+}
+
+// 辞書ファイルを検証してユーザー定義辞書として取り込む
+#[tauri::command]
+fn import_dictionary(
+    path: PathBuf,
+    conflict_resolution: ImportConflictResolution,
+    app: AppHandle,
+    library: State<Mutex<Library>>,
+) -> Result<ImportDictionaryResult, ToUIError> {
+    let mut locked_library = library.lock().unwrap();
+
+    let result = locked_library.import_dictionary(path, conflict_resolution)?;
+    emit_dictionaries_changed(&app, &locked_library)?;
+
+    Ok(result)
+}
+
+// 語彙とその読み全体から辞書の1行に書ける綴りの分け方を提案する
+// 文字ごとの読みは同梱の表に登録されている辞書から集めたものを加えて使う
+#[tauri::command]
+fn propose_reading_split(
+    view: String,
+    reading: String,
+    library: State<Mutex<Library>>,
+) -> Result<ReadingSplitResult, ToUIError> {
+    let locked_library = library.lock().unwrap();
+    let reading_table = ReadingTable::new(locked_library.all_vocabulary_entries());
+
+    split_reading(&view, &reading, &reading_table).ok_or_else(|| {
+        ToUIError::new(
+            ToUIErrorKind::InvalidDictionaryEdit,
+            format!(
+                "view and reading must be at most {} and {} characters",
+                MAX_VIEW_LENGTH, MAX_READING_LENGTH
+            ),
+        )
+    })
+}
+
+// 空のユーザー定義辞書を作成する
+#[tauri::command]
+fn create_dictionary(
+    dictionary_name: String,
+    dictionary_type: DictionaryType,
+    app: AppHandle,
+    library: State<Mutex<Library>>,
+) -> Result<DictionaryInfo, ToUIError> {
+    let mut locked_library = library.lock().unwrap();
+
+    let dictionary_info = locked_library.create_dictionary(&dictionary_name, dictionary_type)?;
+    emit_dictionaries_changed(&app, &locked_library)?;
+
+    Ok(dictionary_info)
+}
+
+// ユーザー定義辞書の末尾に語彙を追加する
+// 語彙は辞書ファイルの1行と同じ形式で渡す
+#[tauri::command]
+fn add_dictionary_entry(
+    dictionary_name: String,
+    dictionary_origin: DictionaryOrigin,
+    dictionary_type: DictionaryType,
+    line: String,
+    app: AppHandle,
+    library: State<Mutex<Library>>,
+) -> Result<DictionaryInfo, ToUIError> {
+    edit_dictionary(
+        &app,
+        &library,
+        &dictionary_name,
+        dictionary_origin,
+        dictionary_type,
+        DictionaryEdit::Add(line),
+    )
+}
+
+// ユーザー定義辞書の指定された行の語彙を置き換える
+#[tauri::command]
+fn update_dictionary_entry(
+    dictionary_name: String,
+    dictionary_origin: DictionaryOrigin,
+    dictionary_type: DictionaryType,
+    line_number: usize,
+    line: String,
+    app: AppHandle,
+    library: State<Mutex<Library>>,
+) -> Result<DictionaryInfo, ToUIError> {
+    edit_dictionary(
+        &app,
+        &library,
+        &dictionary_name,
+        dictionary_origin,
+        dictionary_type,
+        DictionaryEdit::Update(line_number, line),
+    )
+}
+
+// ユーザー定義辞書の指定された行の語彙を削除する
+#[tauri::command]
+fn delete_dictionary_entry(
+    dictionary_name: String,
+    dictionary_origin: DictionaryOrigin,
+    dictionary_type: DictionaryType,
+    line_number: usize,
+    app: AppHandle,
+    library: State<Mutex<Library>>,
+) -> Result<DictionaryInfo, ToUIError> {
+    edit_dictionary(
+        &app,
+        &library,
+        &dictionary_name,
+        dictionary_origin,
+        dictionary_type,
+        DictionaryEdit::Delete(line_number),
+    )
 }
 
 #[tauri::command]
 fn confirm_query(
     query_request_from_ui: QueryRequestFromUI,
     library: State<Mutex<Library>>,
-    typing_engine: State<Mutex<TypingEngine>>,
-) {
+    game_session: State<Mutex<GameSession>>,
+    confirmed_query_request: State<Mutex<Option<QueryRequestFromUI>>>,
+    history: State<Mutex<History>>,
+    vocabulary_tracker: State<Mutex<VocabularyTracker>>,
+    app: AppHandle,
+) -> Result<(), ToUIError> {
+    query_request_from_ui.validate()?;
+
+    // ゴーストと競争する場合には最も速かった回と同じ語彙・順番・終了条件でクエリを構築する
+    let ghost_replay = if query_request_from_ui.ghost_race() {
+        Some(find_ghost_replay(
+            &query_request_from_ui,
+            &history.lock().unwrap(),
+            &app.state::<Replays>(),
+        )?)
+    } else {
+        None
+    };
+    let query_request_from_ui = match &ghost_replay {
+        Some(replay) => query_request_from_ui.with_ghost_vocabularies(replay.query_request()),
+        None => query_request_from_ui,
+    };
+    let ghost_vocabulary_entries = match &ghost_replay {
+        Some(replay) => replay.construct_vocabulary_entries()?,
+        None => vec![],
+    };
+
     let locked_library = library.lock().unwrap();
 
-    let vocabulary_entries = locked_library.vocabulary_entries_of_request(
-        query_request_from_ui.dictionary_type,
-        &query_request_from_ui.used_dictionaries,
-    );
+    let vocabulary_entries = if ghost_replay.is_some() {
+        ghost_vocabulary_entries.iter().collect()
+    } else {
+        locked_library.vocabulary_entries_of_request(
+            query_request_from_ui.dictionary_type().clone(),
+            query_request_from_ui.used_dictionaries(),
+            query_request_from_ui.tag_filter(),
+        )?
+    };
+
+    if vocabulary_entries.is_empty() {
+        return Err(QueryRequestError::NoVocabulary.into());
+    }
+
+    let picker = if let Some(replay) = &ghost_replay {
+        VocabularyPicker::replaying(replay.picked_indices().to_vec())
+    } else if query_request_from_ui.review_mode() {
+        let weakness_scores =
+            construct_weakness_scores(history.lock().unwrap().vocabulary_results());
+
+        let weights: Vec<f64> = vocabulary_entries
+            .iter()
+            .map(|vocabulary_entry| {
+                weakness_scores
+                    .get(vocabulary_entry.view())
+                    .copied()
+                    .unwrap_or(0.0)
+            })
+            .collect();
+
+        VocabularyPicker::weighted(&weights)
+    } else {
+        query_request_from_ui.construct_picker(vocabulary_entries.len())
+    };
+
+    let separator = query_request_from_ui.construct_separator()?;
+    let separator_view = match &separator {
+        VocabularySeparator::None => None,
+        VocabularySeparator::WhiteSpace => Some(" ".to_string()),
+        VocabularySeparator::Vocabulary(vocabulary_entry) => {
+            Some(vocabulary_entry.view().to_string())
+        }
+    };
+
+    let ghost = ghost_replay
+        .as_ref()
+        .map(Replay::construct_ghost)
+        .transpose()?;
+
+    let picked_indices = Rc::new(RefCell::new(vec![]));
 
     let query_request = QueryRequest::new(
         &vocabulary_entries,
-        VocabularyQuantifier::KeyStroke(query_request_from_ui.key_stroke_count_threshold.unwrap()),
-        VocabularySeparator::WhiteSpace,
-        VocabularyOrder::Random,
+        query_request_from_ui.construct_quantifier()?,
+        separator,
+        construct_recording_order(picker, picked_indices.clone()),
     );
 
-    typing_engine.lock().unwrap().init(query_request);
+    let mut locked_game_session = game_session.lock().unwrap();
+    locked_game_session.confirm(query_request, query_request_from_ui.lap_request().clone())?;
+
+    // 語彙区切りがある場合には各語彙の後に入る
+    let sequence = picked_indices
+        .borrow()
+        .iter()
+        .flat_map(|i| {
+            std::iter::once((vocabulary_entries[*i].view().to_string(), false))
+                .chain(separator_view.clone().map(|view| (view, true)))
+        })
+        .collect();
+    *vocabulary_tracker.lock().unwrap() = VocabularyTracker::new(sequence);
+
+    // 打鍵は終了するまで記録していく
+    app.state::<Mutex<Option<Replay>>>()
+        .lock()
+        .unwrap()
+        .replace(Replay::new(
+            query_request_from_ui.clone(),
+            &vocabulary_entries,
+            &picked_indices.borrow(),
+        ));
+
+    *app.state::<Mutex<Option<Ghost>>>().lock().unwrap() = ghost;
+
+    // 終了時に履歴として記録するために保持しておく
+    confirmed_query_request
+        .lock()
+        .unwrap()
+        .replace(query_request_from_ui);
+
+    emit_game_state_changed(&app, locked_game_session.state())?;
+
+    Ok(())
 }
 
 #[tauri::command]
-fn start_game(typing_engine: State<Mutex<TypingEngine>>) -> DisplayInformation {
-    let mut locked_typing_engine = typing_engine.lock().unwrap();
-    locked_typing_engine.start().unwrap();
+fn start_game(
+    game_session: State<Mutex<GameSession>>,
+    key_miss_statistics: State<Mutex<KeyMissStatistics>>,
+    app: AppHandle,
+) -> Result<DisplayInformation, ToUIError> {
+    let mut locked_game_session = game_session.lock().unwrap();
+    locked_game_session.start()?;
 
-    locked_typing_engine
-        .construct_display_info(LapRequest::IdealKeyStroke(NonZeroUsize::new(50).unwrap()))
-        .unwrap()
-        .into()
+    *key_miss_statistics.lock().unwrap() = KeyMissStatistics::new();
+
+    emit_game_state_changed(&app, locked_game_session.state())?;
+
+    let display_info = locked_game_session.construct_display_info()?;
+
+    Ok(construct_display_information(
+        &app,
+        &locked_game_session,
+        &display_info,
+    ))
+}
+
+#[tauri::command]
+fn pause_game(game_session: State<Mutex<GameSession>>, app: AppHandle) -> Result<(), ToUIError> {
+    let mut locked_game_session = game_session.lock().unwrap();
+    locked_game_session.pause()?;
+
+    emit_game_state_changed(&app, locked_game_session.state())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+fn resume_game(game_session: State<Mutex<GameSession>>, app: AppHandle) -> Result<(), ToUIError> {
+    let mut locked_game_session = game_session.lock().unwrap();
+    locked_game_session.resume()?;
+
+    emit_game_state_changed(&app, locked_game_session.state())?;
+
+    Ok(())
+}
+
+// タイピングを中断してクエリの確定前に戻す
+// 中断したタイピングは履歴に残さない
+#[tauri::command]
+fn cancel_game(game_session: State<Mutex<GameSession>>, app: AppHandle) -> Result<(), ToUIError> {
+    let mut locked_game_session = game_session.lock().unwrap();
+    locked_game_session.cancel();
+
+    emit_game_state_changed(&app, locked_game_session.state())?;
+
+    Ok(())
 }
 
 #[tauri::command]
 fn stroke_key(
     key_stroke_info: KeyStrokeInfo,
-    typing_engine: State<Mutex<TypingEngine>>,
+    game_session: State<Mutex<GameSession>>,
+    confirmed_query_request: State<Mutex<Option<QueryRequestFromUI>>>,
+    key_miss_statistics: State<Mutex<KeyMissStatistics>>,
+    vocabulary_tracker: State<Mutex<VocabularyTracker>>,
+    recording_replay: State<Mutex<Option<Replay>>>,
+    app: AppHandle,
 ) -> Result<(bool, DisplayInformation), ToUIError> {
-    assert_eq!(key_stroke_info.key.chars().count(), 1);
-    let key_stroke_char = key_stroke_info.key.chars().next().unwrap();
+    let mut key_chars = key_stroke_info.key.chars();
+    let key_stroke_char = match (key_chars.next(), key_chars.next()) {
+        (Some(c), None) => c,
+        _ => {
+            return Err(ToUIError::new(
+                ToUIErrorKind::InvalidKeyInput,
+                format!("key must be a single character: {:?}", key_stroke_info.key),
+            ))
+        }
+    };
+
+    let mut locked_game_session = game_session.lock().unwrap();
+
+    // 制限時間を過ぎてからの打鍵は受け付けずにその時点で終了とする
+    if let Some(time_limit_ms) = time_limit_ms_of(&confirmed_query_request) {
+        if is_time_up(&locked_game_session, time_limit_ms) {
+            let display_info = locked_game_session.construct_display_info()?;
 
-    let mut locked_typing_engine = typing_engine.lock().unwrap();
-    let finished = locked_typing_engine.stroke_key(key_stroke_char.try_into().unwrap())?;
+            finish_game(
+                &app,
+                &mut locked_game_session,
+                TypingResult::from_time_up(&display_info, time_limit_ms),
+            )?;
+
+            return Ok((
+                true,
+                construct_display_information(&app, &locked_game_session, &display_info),
+            ));
+        }
+    }
+
+    // 打鍵前に期待されていたキー・ミスタイプ数・表示文字列上の位置を控えておき打鍵の結果と比較する
+    let (expected_key, prev_wrong_count, prev_view_position) = {
+        let prev_display_info = locked_game_session.construct_display_info()?;
+        let prev_key_stroke_info = prev_display_info.key_stroke_info();
+
+        (
+            prev_key_stroke_info
+                .key_stroke()
+                .chars()
+                .nth(prev_key_stroke_info.current_cursor_position()),
+            prev_key_stroke_info.on_typing_statistics().wrong_count(),
+            prev_display_info
+                .view_info()
+                .current_cursor_positions()
+                .first()
+                .copied(),
+        )
+    };
+
+    let finished = locked_game_session.stroke_key(key_stroke_char.try_into()?)?;
+    // UI側の経過時間は一時停止していた時間を含むので記録にはバックエンドの経過時間を使う
+    let active_elapsed_time_ms = locked_game_session.active_elapsed_time().as_millis() as usize;
+
+    let display_info = locked_game_session.construct_display_info()?;
+
+    let is_correct = display_info
+        .key_stroke_info()
+        .on_typing_statistics()
+        .wrong_count()
+        == prev_wrong_count;
+
+    if let Some(expected_key) = expected_key {
+        key_miss_statistics
+            .lock()
+            .unwrap()
+            .record(expected_key, key_stroke_char, is_correct);
+    }
+
+    if let Some(prev_view_position) = prev_view_position {
+        let next_view_position = if finished {
+            None
+        } else {
+            display_info
+                .view_info()
+                .current_cursor_positions()
+                .first()
+                .copied()
+        };
+
+        vocabulary_tracker.lock().unwrap().record(
+            prev_view_position,
+            next_view_position,
+            key_stroke_char,
+            is_correct,
+            active_elapsed_time_ms,
+        );
+    }
+
+    if let Some(replay) = recording_replay.lock().unwrap().as_mut() {
+        replay.record(key_stroke_char, active_elapsed_time_ms, is_correct);
+    }
+
+    if finished {
+        let result = locked_game_session.construct_result()?;
+
+        finish_game(&app, &mut locked_game_session, result)?;
+    }
 
     Ok((
         finished,
-        locked_typing_engine
-            .construct_display_info(LapRequest::IdealKeyStroke(NonZeroUsize::new(50).unwrap()))
-            .unwrap()
-            .into(),
+        construct_display_information(&app, &locked_game_session, &display_info),
     ))
 }
 
+// 打鍵のない間もゴーストを進めるためにUI側から位置を取得する
+// 経過時間は一時停止していた時間を除いてバックエンドで計測したものを使う
+#[tauri::command]
+fn get_ghost_position(
+    game_session: State<Mutex<GameSession>>,
+    ghost: State<Mutex<Option<Ghost>>>,
+) -> Result<Option<GhostPosition>, ToUIError> {
+    let elapsed_time_ms = game_session
+        .lock()
+        .unwrap()
+        .active_elapsed_time()
+        .as_millis() as usize;
+
+    Ok(ghost
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|ghost| ghost.position_at(elapsed_time_ms))
+        .cloned())
+}
+
+// 制限時間が来たかどうかをUI側から確認させる
+// 打鍵のない間に制限時間が来た場合にも終了させるためのもので、終了したかどうかを返す
+#[tauri::command]
+fn notify_time_up(
+    game_session: State<Mutex<GameSession>>,
+    confirmed_query_request: State<Mutex<Option<QueryRequestFromUI>>>,
+    app: AppHandle,
+) -> Result<bool, ToUIError> {
+    let mut locked_game_session = game_session.lock().unwrap();
+
+    match locked_game_session.state() {
+        GameState::Typing => {}
+        GameState::Finished => return Ok(true),
+        _ => return Ok(false),
+    }
+
+    let time_limit_ms = match time_limit_ms_of(&confirmed_query_request) {
+        Some(time_limit_ms) if is_time_up(&locked_game_session, time_limit_ms) => time_limit_ms,
+        _ => return Ok(false),
+    };
+
+    let display_info = locked_game_session.construct_display_info()?;
+
+    finish_game(
+        &app,
+        &mut locked_game_session,
+        TypingResult::from_time_up(&display_info, time_limit_ms),
+    )?;
+
+    Ok(true)
+}
+
+// 経過時間は一時停止していた時間を除いてバックエンドで計測したものを使う
+fn is_time_up(game_session: &GameSession, time_limit_ms: usize) -> bool {
+    game_session.active_elapsed_time() >= Duration::from_millis(time_limit_ms as u64)
+}
+
+fn time_limit_ms_of(
+    confirmed_query_request: &State<Mutex<Option<QueryRequestFromUI>>>,
+) -> Option<usize> {
+    confirmed_query_request
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|query_request_from_ui| query_request_from_ui.time_limit_ms())
+}
+
+// タイピングの結果を確定させて履歴とリプレイを保存する
+fn finish_game(
+    app: &AppHandle,
+    game_session: &mut GameSession,
+    mut result: TypingResult,
+) -> Result<(), ToUIError> {
+    let confirmed_query_request = app.state::<Mutex<Option<QueryRequestFromUI>>>();
+    if let Some(query_request_from_ui) = confirmed_query_request.lock().unwrap().as_ref() {
+        result.set_scoring_formula(query_request_from_ui.scoring_formula());
+    }
+
+    let vocabulary_results = app
+        .state::<Mutex<VocabularyTracker>>()
+        .lock()
+        .unwrap()
+        .construct_results();
+    result.set_vocabularies(vocabulary_results.clone());
+
+    game_session.finish(result)?;
+
+    // ラップごとの結果は終了時に加えられるので確定したものを使う
+    let result = game_session.result()?.clone();
+
+    let history = app.state::<Mutex<History>>();
+    let key_miss_statistics = app.state::<Mutex<KeyMissStatistics>>();
+    let recording_replay = app.state::<Mutex<Option<Replay>>>();
+
+    let mut save_warnings = vec![];
+
+    if let Some(query_request_from_ui) = confirmed_query_request.lock().unwrap().as_ref() {
+        if let Ok(quantifier) = query_request_from_ui.quantifier() {
+            let record = HistoryRecord::new(
+                query_request_from_ui.dictionary_type().clone(),
+                query_request_from_ui.used_dictionaries().clone(),
+                quantifier,
+                result,
+                game_session.lap_end_time_ms(),
+                key_miss_statistics.lock().unwrap().clone(),
+                vocabulary_results,
+            );
+
+            // 履歴やリプレイの保存に失敗してもタイピング自体は終了させ、結果と一緒にUI側に伝える
+            match history.lock().unwrap().append(record) {
+                Ok(entry) => {
+                    if let Some(replay) = recording_replay.lock().unwrap().take() {
+                        if let Err(e) = app.state::<Replays>().save(entry.id(), &replay) {
+                            save_warnings.push(SaveWarning::Replay {
+                                message: e.to_string(),
+                            });
+                        }
+                    }
+                }
+                Err(e) => save_warnings.push(SaveWarning::History {
+                    message: e.to_string(),
+                }),
+            }
+        }
+    }
+
+    game_session.set_save_warnings(save_warnings)?;
+
+    // UI側は終了の通知を受けて結果を取得するので保存を終えてから通知する
+    emit_game_state_changed(app, game_session.state())?;
+
+    Ok(())
+}
+
+// 外部のエディタで編集された辞書を反映させるためにユーザー定義辞書のライブラリディレクトリを監視する
+// 辞書の一覧や内容が変わったらUI側に通知する
+// 監視を始められなかった場合でも辞書の一覧を取得する度に読み込み直すので起動は続ける
+fn watch_library(app: AppHandle) -> notify::Result<()> {
+    let library_dirs = app.state::<Mutex<Library>>().lock().unwrap().library_dirs();
+
+    let (sender, receiver) = channel();
+    let mut watcher = recommended_watcher(sender)?;
+    watcher.watch(library_dirs.user_defined(), RecursiveMode::Recursive)?;
+
+    thread::spawn(move || {
+        // スレッドが続く間は監視を止めない
+        let _watcher = watcher;
+
+        while receiver.recv().is_ok() {
+            while receiver.recv_timeout(DICTIONARY_WATCH_DEBOUNCE).is_ok() {}
+
+            // ディレクトリの走査中はライブラリをロックしない
+            let dictionary_files = scan_dictionary_files(&library_dirs);
+
+            let library = app.state::<Mutex<Library>>();
+            let mut locked_library = library.lock().unwrap();
+
+            // 通知に失敗してもUI側は次に辞書の一覧を取得した時に最新のものを得られる
+            if locked_library.reload_dictionaries_from(dictionary_files) {
+                emit_dictionaries_changed(&app, &locked_library).ok();
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn edit_dictionary(
+    app: &AppHandle,
+    library: &Mutex<Library>,
+    dictionary_name: &str,
+    dictionary_origin: DictionaryOrigin,
+    dictionary_type: DictionaryType,
+    edit: DictionaryEdit,
+) -> Result<DictionaryInfo, ToUIError> {
+    let mut locked_library = library.lock().unwrap();
+
+    let dictionary_info = locked_library.edit_dictionary(
+        dictionary_name,
+        dictionary_origin,
+        dictionary_type,
+        edit,
+    )?;
+    emit_dictionaries_changed(app, &locked_library)?;
+
+    Ok(dictionary_info)
+}
+
+// 辞書の一覧や内容の変化をUI側に通知する
+fn emit_dictionaries_changed(app: &AppHandle, library: &Library) -> tauri::Result<()> {
+    app.emit_all("dictionaries_changed", library.dictionary_infos())
+}
+
+// 進行状態の遷移をUI側に通知する
+fn emit_game_state_changed(app: &AppHandle, state: GameState) -> tauri::Result<()> {
+    app.emit_all("game_state_changed", state)
+}
+
 #[tauri::command]
-fn get_result(typing_engine: State<Mutex<TypingEngine>>) -> Result<TypingResult, ToUIError> {
-    let locked_typing_engine = typing_engine.lock().unwrap();
+fn get_result(game_session: State<Mutex<GameSession>>) -> Result<TypingResult, ToUIError> {
+    Ok(game_session.lock().unwrap().result()?.clone())
+}
 
-    Ok(locked_typing_engine
-        .construst_result_statistics(LapRequest::IdealKeyStroke(NonZeroUsize::new(50).unwrap()))
+#[tauri::command]
+fn get_history_entries(
+    filter: Option<HistoryFilter>,
+    history: State<Mutex<History>>,
+) -> Result<Vec<HistoryEntry>, ToUIError> {
+    Ok(history
+        .lock()
         .unwrap()
-        .into())
+        .filtered_entries(&filter.unwrap_or_default())
+        .into_iter()
+        .cloned()
+        .collect())
+}
+
+#[tauri::command]
+fn delete_history_entries(
+    ids: Vec<u64>,
+    history: State<Mutex<History>>,
+    replays: State<Replays>,
+) -> Result<usize, ToUIError> {
+    let deleted_count = history.lock().unwrap().delete(&ids)?;
+    replays.delete(&ids)?;
+
+    Ok(deleted_count)
+}
+
+// 履歴に保存したリプレイを再生し打鍵ごとの表示情報を返す
+#[tauri::command]
+fn replay_history_entry(id: u64, replays: State<Replays>) -> Result<Vec<ReplayFrame>, ToUIError> {
+    Ok(replays.load(id)?.play()?)
+}
+
+#[tauri::command]
+fn get_key_miss_heat_map(
+    scope: KeyMissScope,
+    history: State<Mutex<History>>,
+    key_miss_statistics: State<Mutex<KeyMissStatistics>>,
+) -> Result<KeyMissHeatMap, ToUIError> {
+    Ok(match scope {
+        KeyMissScope::CurrentSession => key_miss_statistics.lock().unwrap().construct_heat_map(),
+        KeyMissScope::History { filter } => history
+            .lock()
+            .unwrap()
+            .aggregate_key_miss_statistics(&filter)
+            .construct_heat_map(),
+    })
+}
+
+// 結果を指定されたパスにCSVかJSONで書き出し書き出した件数を返す
+#[tauri::command]
+fn export_results(
+    scope: ExportScope,
+    format: ExportFormat,
+    path: PathBuf,
+    game_session: State<Mutex<GameSession>>,
+    confirmed_query_request: State<Mutex<Option<QueryRequestFromUI>>>,
+    history: State<Mutex<History>>,
+) -> Result<usize, ToUIError> {
+    let rows: Vec<ExportRow> = match scope {
+        ExportScope::CurrentResult => {
+            let locked_game_session = game_session.lock().unwrap();
+            let result = locked_game_session.result()?;
+
+            let locked_confirmed_query_request = confirmed_query_request.lock().unwrap();
+            let query_request_from_ui =
+                locked_confirmed_query_request.as_ref().ok_or_else(|| {
+                    ToUIError::new(ToUIErrorKind::InvalidGameState, "no query is confirmed")
+                })?;
+
+            vec![ExportRow::new(
+                query_request_from_ui.dictionary_type(),
+                query_request_from_ui.used_dictionaries(),
+                query_request_from_ui.quantifier().ok().as_ref(),
+                result,
+            )]
+        }
+        ExportScope::History { filter } => history
+            .lock()
+            .unwrap()
+            .filtered_entries(&filter)
+            .into_iter()
+            .map(ExportRow::from)
+            .collect(),
+    };
+
+    write_rows(path, format, &rows)?;
+
+    Ok(rows.len())
+}
+
+// 同じ辞書と終了条件で打った履歴のうちリプレイが残っている最も速いものを探す
+fn find_ghost_replay(
+    query_request_from_ui: &QueryRequestFromUI,
+    history: &History,
+    replays: &Replays,
+) -> Result<Replay, ToUIError> {
+    history
+        .entries_by_speed(
+            query_request_from_ui.dictionary_type(),
+            query_request_from_ui.used_dictionaries(),
+            &query_request_from_ui.quantifier()?,
+        )
+        .iter()
+        .filter_map(|entry| replays.load(entry.id()).ok())
+        // タグで絞り込んだ語彙が異なると同じ条件で競争できない
+        .find(|replay| replay.query_request().tag_filter() == query_request_from_ui.tag_filter())
+        .ok_or_else(|| QueryRequestError::GhostNotFound.into())
+}
+
+// ゴーストと競争している場合には同じ経過時間でのゴーストの位置を表示情報に加える
+// 経過時間は一時停止していた時間を除いてバックエンドで計測したものを使う
+fn construct_display_information(
+    app: &AppHandle,
+    game_session: &GameSession,
+    display_info: &DisplayInfo,
+) -> DisplayInformation {
+    let elapsed_time_ms = game_session.active_elapsed_time().as_millis() as usize;
+    let mut display_information =
+        DisplayInformation::new(display_info, game_session.lap_end_time_ms());
+
+    display_information.set_ghost(
+        app.state::<Mutex<Option<Ghost>>>()
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|ghost| ghost.position_at(elapsed_time_ms))
+            .cloned(),
+    );
+
+    display_information
 }
 
 fn main() {
     tauri::Builder::default()
         .setup(|app| {
-            let typing_engine = TypingEngine::new();
-            app.manage(Mutex::new(typing_engine));
+            app.manage(Mutex::new(GameSession::new()));
 
             // アプリケーション用のディレクトリが無かったら作る
             let app_dir = app.path_resolver().app_dir().unwrap();
@@ -138,6 +887,14 @@ fn main() {
                 .unwrap();
 
             app.manage(Mutex::new(Library::new(app.path_resolver())));
+            watch_library(app.handle()).ok();
+            app.manage(Mutex::new(History::new(app.path_resolver())));
+            app.manage(Mutex::new(None::<QueryRequestFromUI>));
+            app.manage(Mutex::new(KeyMissStatistics::new()));
+            app.manage(Mutex::new(VocabularyTracker::default()));
+            app.manage(Replays::new(app.path_resolver()));
+            app.manage(Mutex::new(None::<Replay>));
+            app.manage(Mutex::new(None::<Ghost>));
 
             #[cfg(debug_assertions)]
             app.get_window("main").unwrap().open_devtools();
@@ -146,10 +903,27 @@ fn main() {
         })
         .invoke_handler(generate_handler![
             get_dictionary_infos,
+            set_spell_normalization,
+            import_dictionary,
+            create_dictionary,
+            add_dictionary_entry,
+            update_dictionary_entry,
+            delete_dictionary_entry,
+            propose_reading_split,
             confirm_query,
             start_game,
+            cancel_game,
+            pause_game,
+            resume_game,
             stroke_key,
-            get_result
+            get_ghost_position,
+            notify_time_up,
+            get_result,
+            get_history_entries,
+            delete_history_entries,
+            replay_history_entry,
+            export_results,
+            get_key_miss_heat_map
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,435 @@
+use std::{error::Error, fmt::Display, num::NonZeroUsize};
+
+use serde::{Deserialize, Serialize};
+use typing_engine::{LapRequest, VocabularyQuantifier, VocabularySeparator};
+
+use crate::library::{
+    parse_vocabulary_entry, DictionaryOrigin, DictionaryType, ParseDiagnostic, TagFilter,
+};
+use crate::scoring::ScoringFormula;
+use crate::vocabulary_order::VocabularyPicker;
+
+// 制限時間のあるタイピングで想定する最大の打鍵速度
+const MAX_KEY_STROKES_PER_SECOND: usize = 20;
+// 制限時間の上限で、これを超える分の打鍵数は最初に構築しきれない
+const MAX_TIME_LIMIT_SECONDS: usize = 60 * 60;
+
+// UI側から渡されるクエリの要求
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct QueryRequestFromUI {
+    dictionary_type: DictionaryType,
+    used_dictionaries: Vec<(DictionaryOrigin, String)>,
+    // quantifierが指定されていない場合に打鍵数で終了させるための閾値
+    key_stroke_count_threshold: Option<NonZeroUsize>,
+    #[serde(default)]
+    quantifier: Option<VocabularyQuantifierFromUI>,
+    // 過去にミスタイプしたり時間のかかった語彙ほど出やすくする
+    #[serde(default)]
+    review_mode: bool,
+    #[serde(default)]
+    order: VocabularyOrderFromUI,
+    #[serde(default)]
+    separator: VocabularySeparatorFromUI,
+    // 同じ辞書と終了条件で最も速かった回のリプレイをゴーストとして同じ語彙で競争する
+    #[serde(default)]
+    ghost_race: bool,
+    // 表示と結果の両方でラップをこの単位で区切る
+    #[serde(default)]
+    lap_request: LapRequestFromUI,
+    // 使う辞書の語彙をタグで絞り込む
+    #[serde(default)]
+    tag_filter: TagFilter,
+    // 結果の主なスコアの算出方法
+    #[serde(default)]
+    scoring_formula: ScoringFormula,
+}
+
+impl QueryRequestFromUI {
+    pub(crate) fn dictionary_type(&self) -> &DictionaryType {
+        &self.dictionary_type
+    }
+
+    pub(crate) fn used_dictionaries(&self) -> &Vec<(DictionaryOrigin, String)> {
+        &self.used_dictionaries
+    }
+
+    // タイピングを終了する条件
+    // 明示的な指定がなければ打鍵数の閾値を使い、それもなければ文章は1つ打ち終えたら終了とする
+    pub(crate) fn quantifier(&self) -> Result<VocabularyQuantifierFromUI, QueryRequestError> {
+        if let Some(quantifier) = &self.quantifier {
+            if let VocabularyQuantifierFromUI::TimeLimit { seconds } = quantifier {
+                if seconds.get() > MAX_TIME_LIMIT_SECONDS {
+                    return Err(QueryRequestError::TimeLimitTooLong);
+                }
+            }
+
+            return Ok(quantifier.clone());
+        }
+
+        if let Some(count) = self.key_stroke_count_threshold {
+            return Ok(VocabularyQuantifierFromUI::KeyStroke { count });
+        }
+
+        match self.dictionary_type {
+            DictionaryType::Sentence => Ok(VocabularyQuantifierFromUI::Vocabulary {
+                count: NonZeroUsize::new(1).unwrap(),
+            }),
+            DictionaryType::Word => Err(QueryRequestError::MissingQuantifier),
+        }
+    }
+
+    // 制限時間がある場合にはミリ秒で返す
+    pub(crate) fn time_limit_ms(&self) -> Option<usize> {
+        match self.quantifier() {
+            Ok(VocabularyQuantifierFromUI::TimeLimit { seconds }) => Some(seconds.get() * 1000),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn review_mode(&self) -> bool {
+        self.review_mode
+    }
+
+    pub(crate) fn ghost_race(&self) -> bool {
+        self.ghost_race
+    }
+
+    pub(crate) fn lap_request(&self) -> &LapRequestFromUI {
+        &self.lap_request
+    }
+
+    pub(crate) fn tag_filter(&self) -> &TagFilter {
+        &self.tag_filter
+    }
+
+    pub(crate) fn scoring_formula(&self) -> ScoringFormula {
+        self.scoring_formula
+    }
+
+    // ゴーストと同じ語彙・順番・終了条件・語彙区切りで競争するためにそれらのみをリプレイの要求から引き継ぐ
+    // スコアの算出方法やラップなどそれ以外はUI側で指定されたものを使う
+    pub(crate) fn with_ghost_vocabularies(&self, ghost_query_request: &Self) -> Self {
+        Self {
+            dictionary_type: ghost_query_request.dictionary_type.clone(),
+            used_dictionaries: ghost_query_request.used_dictionaries.clone(),
+            key_stroke_count_threshold: ghost_query_request.key_stroke_count_threshold,
+            quantifier: ghost_query_request.quantifier.clone(),
+            order: ghost_query_request.order.clone(),
+            separator: ghost_query_request.separator.clone(),
+            tag_filter: ghost_query_request.tag_filter.clone(),
+            ..self.clone()
+        }
+    }
+
+    // 要求の組み合わせとして不正なものがないかを確認する
+    pub(crate) fn validate(&self) -> Result<(), QueryRequestError> {
+        // 復習モードは語彙の出やすさを変えるのでランダム以外の順番とは両立しない
+        if self.review_mode && self.order != VocabularyOrderFromUI::Random {
+            return Err(QueryRequestError::ReviewModeRequiresRandomOrder);
+        }
+
+        self.quantifier()?;
+        self.construct_separator().map(|_| ())
+    }
+
+    // タイピングエンジンに渡すクエリの量を構築する
+    pub(crate) fn construct_quantifier(&self) -> Result<VocabularyQuantifier, QueryRequestError> {
+        let separated = self.separator != VocabularySeparatorFromUI::None;
+
+        Ok(self.quantifier()?.construct_quantifier(separated))
+    }
+
+    // 語彙の選び方を構築する
+    pub(crate) fn construct_picker(&self, vocabulary_count: usize) -> VocabularyPicker {
+        match self.order {
+            VocabularyOrderFromUI::Random => VocabularyPicker::Random,
+            VocabularyOrderFromUI::InOrder => VocabularyPicker::InOrder,
+            VocabularyOrderFromUI::Reversed => VocabularyPicker::Reversed,
+            VocabularyOrderFromUI::SeededShuffle { seed } => {
+                VocabularyPicker::seeded_shuffle(seed, vocabulary_count)
+            }
+        }
+    }
+
+    pub(crate) fn construct_separator(&self) -> Result<VocabularySeparator, QueryRequestError> {
+        match &self.separator {
+            VocabularySeparatorFromUI::None => Ok(VocabularySeparator::None),
+            VocabularySeparatorFromUI::WhiteSpace => Ok(VocabularySeparator::WhiteSpace),
+            VocabularySeparatorFromUI::Vocabulary { entry } => parse_vocabulary_entry(entry)
+                .map(VocabularySeparator::Vocabulary)
+                .map_err(QueryRequestError::InvalidSeparator),
+        }
+    }
+}
+
+// タイピングを終了する条件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub(crate) enum VocabularyQuantifierFromUI {
+    // 指定した打鍵数分の語彙を打ち終えたら終了する
+    KeyStroke { count: NonZeroUsize },
+    // 指定した数の語彙を打ち終えたら終了する
+    Vocabulary { count: NonZeroUsize },
+    // 指定した秒数が経過したら終了する
+    TimeLimit { seconds: NonZeroUsize },
+}
+
+impl VocabularyQuantifierFromUI {
+    // タイピングエンジンに渡すクエリの量を構築する
+    // エンジンは区切りも1語彙と数えるので、区切りがある場合には語彙の間の区切りの分を足す
+    // 制限時間の場合には時間内に打ち切れないだけの打鍵数を用意しておく
+    fn construct_quantifier(&self, separated: bool) -> VocabularyQuantifier {
+        match self {
+            Self::KeyStroke { count } => VocabularyQuantifier::KeyStroke(*count),
+            Self::Vocabulary { count } if separated => VocabularyQuantifier::Vocabulary(
+                NonZeroUsize::new(count.get().saturating_mul(2) - 1).unwrap(),
+            ),
+            Self::Vocabulary { count } => VocabularyQuantifier::Vocabulary(*count),
+            Self::TimeLimit { seconds } => VocabularyQuantifier::KeyStroke(
+                NonZeroUsize::new(seconds.get().saturating_mul(MAX_KEY_STROKES_PER_SECOND))
+                    .unwrap(),
+            ),
+        }
+    }
+}
+
+// ラップを区切る単位とラップあたりの数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub(crate) enum LapRequestFromUI {
+    // 実際に打った打鍵列での打鍵数
+    KeyStroke { count: NonZeroUsize },
+    // 最短の打鍵列での打鍵数
+    IdealKeyStroke { count: NonZeroUsize },
+    // 綴りの文字数
+    Spell { count: NonZeroUsize },
+}
+
+impl LapRequestFromUI {
+    // LapRequestはCloneできないので使うたびに構築する
+    pub(crate) fn construct_lap_request(&self) -> LapRequest {
+        match self {
+            Self::KeyStroke { count } => LapRequest::KeyStroke(*count),
+            Self::IdealKeyStroke { count } => LapRequest::IdealKeyStroke(*count),
+            Self::Spell { count } => LapRequest::Spell(*count),
+        }
+    }
+}
+
+impl Default for LapRequestFromUI {
+    fn default() -> Self {
+        Self::IdealKeyStroke {
+            count: NonZeroUsize::new(50).unwrap(),
+        }
+    }
+}
+
+// 語彙を選ぶ順番
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub(crate) enum VocabularyOrderFromUI {
+    Random,
+    // 辞書に書かれている順
+    InOrder,
+    // 辞書に書かれている順の逆
+    Reversed,
+    // シードから決まる順番に並べ替えた順
+    SeededShuffle { seed: u64 },
+}
+
+impl Default for VocabularyOrderFromUI {
+    fn default() -> Self {
+        Self::Random
+    }
+}
+
+// 語彙同士の区切り
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub(crate) enum VocabularySeparatorFromUI {
+    // 区切りを入れずに続けて打つ
+    None,
+    WhiteSpace,
+    // 辞書の1行と同じ形式で与えた語彙を区切りとする
+    Vocabulary { entry: String },
+}
+
+impl Default for VocabularySeparatorFromUI {
+    fn default() -> Self {
+        Self::WhiteSpace
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum QueryRequestError {
+    NoVocabulary,
+    MissingQuantifier,
+    ReviewModeRequiresRandomOrder,
+    InvalidSeparator(ParseDiagnostic),
+    TimeLimitTooLong,
+    // 競争相手にできるリプレイがない
+    GhostNotFound,
+}
+
+impl Display for QueryRequestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoVocabulary => write!(f, "no vocabulary is selected"),
+            Self::MissingQuantifier => write!(f, "quantifier is not specified"),
+            Self::ReviewModeRequiresRandomOrder => {
+                write!(f, "review mode can only be used with random order")
+            }
+            Self::InvalidSeparator(diagnostic) => {
+                write!(f, "separator is invalid: {:?}", diagnostic)
+            }
+            Self::TimeLimitTooLong => write!(
+                f,
+                "time limit must be at most {} seconds",
+                MAX_TIME_LIMIT_SECONDS
+            ),
+            Self::GhostNotFound => write!(f, "no replay to race against is found"),
+        }
+    }
+}
+
+impl Error for QueryRequestError {}
+
+#[cfg(test)]
+mod test {
+    use typing_engine::{QueryRequest, TypingEngine, VocabularyOrder};
+
+    use super::*;
+
+    #[test]
+    fn quantifier_1() {
+        let word: QueryRequestFromUI = serde_json::from_str(
+            r#"{"dictionaryType":"word","usedDictionaries":[],"keyStrokeCountThreshold":150}"#,
+        )
+        .unwrap();
+        let sentence: QueryRequestFromUI =
+            serde_json::from_str(r#"{"dictionaryType":"sentence","usedDictionaries":[]}"#).unwrap();
+        let time_limited: QueryRequestFromUI = serde_json::from_str(
+            r#"{"dictionaryType":"word","usedDictionaries":[],"quantifier":{"type":"timeLimit","seconds":60}}"#,
+        )
+        .unwrap();
+        let missing: QueryRequestFromUI =
+            serde_json::from_str(r#"{"dictionaryType":"word","usedDictionaries":[]}"#).unwrap();
+
+        assert_eq!(
+            word.quantifier(),
+            Ok(VocabularyQuantifierFromUI::KeyStroke {
+                count: NonZeroUsize::new(150).unwrap()
+            })
+        );
+        assert_eq!(
+            sentence.quantifier(),
+            Ok(VocabularyQuantifierFromUI::Vocabulary {
+                count: NonZeroUsize::new(1).unwrap()
+            })
+        );
+        assert_eq!(time_limited.time_limit_ms(), Some(60000));
+        assert_eq!(
+            missing.quantifier(),
+            Err(QueryRequestError::MissingQuantifier)
+        );
+    }
+
+    #[test]
+    fn construct_quantifier_1() {
+        let vocabulary_entry = parse_vocabulary_entry("犬:いぬ").unwrap();
+        let vocabulary_entries = vec![&vocabulary_entry];
+
+        let word_views = |json: &str| {
+            let query_request_from_ui: QueryRequestFromUI = serde_json::from_str(json).unwrap();
+            let mut typing_engine = TypingEngine::new();
+            typing_engine.init(QueryRequest::new(
+                &vocabulary_entries,
+                query_request_from_ui.construct_quantifier().unwrap(),
+                query_request_from_ui.construct_separator().unwrap(),
+                VocabularyOrder::InOrder,
+            ));
+            typing_engine.start().unwrap();
+
+            typing_engine
+                .construct_display_info(LapRequest::Spell(NonZeroUsize::new(1).unwrap()))
+                .unwrap()
+                .view_info()
+                .view()
+                .to_string()
+        };
+
+        // 区切りの有無に関わらず指定した数の語彙を打つ
+        assert_eq!(
+            word_views(
+                r#"{"dictionaryType":"word","usedDictionaries":[],"quantifier":{"type":"vocabulary","count":3}}"#
+            ),
+            "犬 犬 犬"
+        );
+        assert_eq!(
+            word_views(
+                r#"{"dictionaryType":"word","usedDictionaries":[],"quantifier":{"type":"vocabulary","count":3},"separator":{"type":"none"}}"#
+            ),
+            "犬犬犬"
+        );
+        assert_eq!(
+            word_views(
+                r#"{"dictionaryType":"word","usedDictionaries":[],"quantifier":{"type":"vocabulary","count":1}}"#
+            ),
+            "犬"
+        );
+    }
+
+    #[test]
+    fn quantifier_2() {
+        let longest: QueryRequestFromUI = serde_json::from_str(
+            r#"{"dictionaryType":"word","usedDictionaries":[],"quantifier":{"type":"timeLimit","seconds":3600}}"#,
+        )
+        .unwrap();
+        let too_long: QueryRequestFromUI = serde_json::from_str(
+            r#"{"dictionaryType":"word","usedDictionaries":[],"quantifier":{"type":"timeLimit","seconds":3601}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(longest.validate(), Ok(()));
+        assert_eq!(
+            too_long.validate(),
+            Err(QueryRequestError::TimeLimitTooLong)
+        );
+        assert_eq!(
+            too_long.construct_quantifier(),
+            Err(QueryRequestError::TimeLimitTooLong)
+        );
+    }
+
+    #[test]
+    fn with_ghost_vocabularies_1() {
+        let from_ui: QueryRequestFromUI = serde_json::from_str(
+            r#"{"dictionaryType":"word","usedDictionaries":[],"quantifier":{"type":"vocabulary","count":5},"ghostRace":true,"lapRequest":{"type":"spell","count":10},"scoringFormula":"netWpm"}"#,
+        )
+        .unwrap();
+        let ghost: QueryRequestFromUI = serde_json::from_str(
+            r#"{"dictionaryType":"word","usedDictionaries":[],"quantifier":{"type":"vocabulary","count":5},"order":{"type":"seededShuffle","seed":42},"separator":{"type":"none"},"lapRequest":{"type":"keyStroke","count":20}}"#,
+        )
+        .unwrap();
+
+        let merged = from_ui.with_ghost_vocabularies(&ghost);
+
+        // 語彙の選び方と区切りはゴーストのもの
+        assert_eq!(
+            merged.order,
+            VocabularyOrderFromUI::SeededShuffle { seed: 42 }
+        );
+        assert_eq!(merged.separator, VocabularySeparatorFromUI::None);
+        assert_eq!(merged.quantifier(), ghost.quantifier());
+        // それ以外はUI側で指定されたもの
+        assert_eq!(
+            merged.lap_request(),
+            &LapRequestFromUI::Spell {
+                count: NonZeroUsize::new(10).unwrap()
+            }
+        );
+        assert_eq!(merged.scoring_formula(), ScoringFormula::NetWpm);
+        assert!(merged.ghost_race());
+    }
+}
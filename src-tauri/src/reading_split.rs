@@ -0,0 +1,524 @@
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+
+use serde::{Deserialize, Serialize};
+use typing_engine::{SpellString, VocabularyEntry, VocabularySpellElement};
+
+use crate::kana::{is_kana, katakana_to_hiragana, semi_voiced, voiced};
+use crate::library::serialize_vocabulary_entry;
+
+// 辞書から読みを引けずに推定した場合の1文字あたりのコスト
+const ESTIMATED_COST_PER_CHAR: usize = 10;
+// 連濁や促音化した読みを使う場合のコスト
+const VARIANT_COST: usize = 1;
+// 提案する分け方の最大数
+const MAX_PROPOSALS: usize = 10;
+// 分け方を提案できる語彙と読みの最大の文字数
+// 状態数が語彙と読みの文字数の積になるので長すぎるものは受け付けない
+pub(crate) const MAX_VIEW_LENGTH: usize = 32;
+pub(crate) const MAX_READING_LENGTH: usize = 64;
+// 読みを推定する場合の1文字あたりの最大の読みの文字数（ex. 承る -> うけたまわ,る）
+const MAX_ESTIMATED_READING_LENGTH_PER_CHAR: usize = 5;
+
+// 同梱の漢字ごとの読みの表で、各行は「漢字 読み 読み ...」
+const BUNDLED_KANJI_READINGS: &str = include_str!("kanji_readings.txt");
+
+// 文字（熟字訓の場合には複数文字）ごとの読み
+// 同梱の表に辞書の語彙から集めた読みを加えたもの
+pub(crate) struct ReadingTable {
+    readings: HashMap<String, Vec<String>>,
+    // 熟字訓として登録されている最も長い文字数
+    max_key_length: usize,
+}
+
+impl ReadingTable {
+    pub(crate) fn new<'a>(
+        vocabulary_entries: impl IntoIterator<Item = &'a VocabularyEntry>,
+    ) -> Self {
+        let mut table = Self::bundled();
+
+        for vocabulary_entry in vocabulary_entries {
+            let mut view_chars = vocabulary_entry.view().chars();
+
+            for spell in vocabulary_entry.spells() {
+                let (spell, count): (&str, usize) = match spell {
+                    VocabularySpellElement::Normal(spell) => (spell, 1),
+                    VocabularySpellElement::Compound((spell, count)) => (spell, count.get()),
+                };
+                let key: String = view_chars.by_ref().take(count).collect();
+
+                // かなはそのまま読みになるので表に載せない
+                if key.chars().all(is_kana) || spell.is_empty() {
+                    continue;
+                }
+
+                table.insert(key, spell);
+            }
+        }
+
+        table
+    }
+
+    fn bundled() -> Self {
+        let mut table = Self {
+            readings: HashMap::new(),
+            max_key_length: 1,
+        };
+
+        for line in BUNDLED_KANJI_READINGS.lines() {
+            if line.starts_with('#') {
+                continue;
+            }
+
+            let mut columns = line.split_whitespace();
+            if let Some(key) = columns.next() {
+                for reading in columns {
+                    table.insert(key.to_string(), reading);
+                }
+            }
+        }
+
+        table
+    }
+
+    fn insert(&mut self, key: String, reading: &str) {
+        self.max_key_length = self.max_key_length.max(key.chars().count());
+
+        let key_readings = self.readings.entry(key).or_default();
+        if !key_readings
+            .iter()
+            .any(|key_reading| key_reading == reading)
+        {
+            key_readings.push(reading.to_string());
+        }
+    }
+
+    // 登録されている読みに連濁・促音化したものを加えた読みの候補とそのコスト
+    fn candidates(&self, key: &str) -> Vec<(String, usize)> {
+        let mut candidates: Vec<(String, usize)> = vec![];
+
+        for reading in self.readings.get(key).into_iter().flatten() {
+            candidates.push((reading.clone(), 0));
+
+            let variants = rendaku_variants(reading)
+                .into_iter()
+                .chain(sokuon_variant(reading))
+                .collect::<Vec<String>>();
+
+            for variant in variants {
+                if !candidates
+                    .iter()
+                    .any(|(candidate, _)| *candidate == variant)
+                {
+                    candidates.push((variant, VARIANT_COST));
+                }
+            }
+        }
+
+        candidates
+    }
+}
+
+// 読みの分け方の候補
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReadingSplit {
+    segments: Vec<ReadingSegment>,
+    // そのまま辞書に書ける1行
+    line: String,
+    // 辞書から読みを引けずに推定したまとまりを含む
+    estimated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReadingSegment {
+    view: String,
+    spell: String,
+    estimated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReadingSplitResult {
+    // 最も尤もらしい分け方で複数ある場合には全て（最大MAX_PROPOSALS個）
+    splits: Vec<ReadingSplit>,
+    // 同じくらい尤もらしい分け方が複数ある
+    ambiguous: bool,
+}
+
+// 語彙とその読み全体から1文字ずつ（熟字訓は1まとまり）の綴りへの分け方を提案する
+// かなはそのまま読みとし、それ以外は辞書から集めた読みを使い、引けなければ残りの読みから推定する
+// 語彙か読みが長すぎる場合にはNoneを返す
+pub(crate) fn split_reading(
+    view: &str,
+    reading: &str,
+    table: &ReadingTable,
+) -> Option<ReadingSplitResult> {
+    let view: Vec<char> = view.chars().collect();
+    let reading: Vec<char> = reading.trim().chars().map(katakana_to_hiragana).collect();
+
+    if view.len() > MAX_VIEW_LENGTH || reading.len() > MAX_READING_LENGTH {
+        return None;
+    }
+
+    let mut splitter = Splitter {
+        view: &view,
+        reading: &reading,
+        table,
+        min_costs: HashMap::new(),
+    };
+
+    let mut splits: Vec<ReadingSplit> = vec![];
+
+    if splitter.min_cost(0, 0).is_some() {
+        let mut segments = vec![];
+        splitter.collect_splits(0, 0, &mut segments, &mut splits);
+    }
+
+    Some(ReadingSplitResult {
+        ambiguous: splits.len() > 1,
+        splits,
+    })
+}
+
+// 語彙の何文字目・読みの何文字目まで分け終えたかを状態として最小のコストを求める
+struct Splitter<'a> {
+    view: &'a [char],
+    reading: &'a [char],
+    table: &'a ReadingTable,
+    min_costs: HashMap<(usize, usize), Option<usize>>,
+}
+
+// 1つのまとまりの分け方
+struct Transition {
+    view_length: usize,
+    reading_length: usize,
+    cost: usize,
+    estimated: bool,
+}
+
+impl Splitter<'_> {
+    fn min_cost(&mut self, i: usize, j: usize) -> Option<usize> {
+        if i == self.view.len() {
+            return if j == self.reading.len() {
+                Some(0)
+            } else {
+                None
+            };
+        }
+
+        if let Some(min_cost) = self.min_costs.get(&(i, j)) {
+            return *min_cost;
+        }
+
+        let min_cost = self
+            .transitions(i, j)
+            .into_iter()
+            .filter_map(|transition| {
+                self.min_cost(i + transition.view_length, j + transition.reading_length)
+                    .map(|cost| cost + transition.cost)
+            })
+            .min();
+
+        self.min_costs.insert((i, j), min_cost);
+
+        min_cost
+    }
+
+    fn transitions(&self, i: usize, j: usize) -> Vec<Transition> {
+        let mut transitions = vec![];
+        let rest_reading: String = self.reading[j..].iter().collect();
+
+        // かなや記号などは読みと同じであればそのまま読みとする
+        if self.reading.get(j) == Some(&katakana_to_hiragana(self.view[i])) {
+            transitions.push(Transition {
+                view_length: 1,
+                reading_length: 1,
+                cost: 0,
+                estimated: false,
+            });
+        }
+
+        if is_kana(self.view[i]) {
+            return transitions;
+        }
+
+        let max_key_length = self.table.max_key_length.min(self.view.len() - i);
+        for key_length in 1..=max_key_length {
+            let key: String = self.view[i..i + key_length].iter().collect();
+
+            for (candidate, cost) in self.table.candidates(&key) {
+                if rest_reading.starts_with(&candidate) {
+                    transitions.push(Transition {
+                        view_length: key_length,
+                        reading_length: candidate.chars().count(),
+                        cost,
+                        estimated: false,
+                    });
+                }
+            }
+        }
+
+        // かな以外が続く部分は任意の長さの読みをひとまとまりとして推定する
+        let non_kana_length = self.view[i..].iter().take_while(|c| !is_kana(**c)).count();
+        for view_length in 1..=non_kana_length {
+            let max_reading_length =
+                (self.reading.len() - j).min(MAX_ESTIMATED_READING_LENGTH_PER_CHAR * view_length);
+
+            for reading_length in 1..=max_reading_length {
+                transitions.push(Transition {
+                    view_length,
+                    reading_length,
+                    cost: ESTIMATED_COST_PER_CHAR * view_length,
+                    estimated: true,
+                });
+            }
+        }
+
+        transitions
+    }
+
+    // 最小のコストになる分け方だけを集める
+    fn collect_splits(
+        &mut self,
+        i: usize,
+        j: usize,
+        segments: &mut Vec<ReadingSegment>,
+        splits: &mut Vec<ReadingSplit>,
+    ) {
+        if splits.len() >= MAX_PROPOSALS {
+            return;
+        }
+
+        if i == self.view.len() {
+            if let Some(split) = construct_split(segments) {
+                if !splits.contains(&split) {
+                    splits.push(split);
+                }
+            }
+            return;
+        }
+
+        let min_cost = self.min_cost(i, j);
+
+        for transition in self.transitions(i, j) {
+            let (next_i, next_j) = (i + transition.view_length, j + transition.reading_length);
+
+            if self
+                .min_cost(next_i, next_j)
+                .map(|cost| cost + transition.cost)
+                != min_cost
+            {
+                continue;
+            }
+
+            segments.push(ReadingSegment {
+                view: self.view[i..next_i].iter().collect(),
+                spell: self.reading[j..next_j].iter().collect(),
+                estimated: transition.estimated,
+            });
+            self.collect_splits(next_i, next_j, segments, splits);
+            segments.pop();
+        }
+    }
+}
+
+// 綴りとして使えない文字を含む読みの場合には辞書の1行にできないのでNoneを返す
+fn construct_split(segments: &[ReadingSegment]) -> Option<ReadingSplit> {
+    let spells = segments
+        .iter()
+        .map(|segment| {
+            let spell = SpellString::try_from(segment.spell.clone()).ok()?;
+            let count = segment.view.chars().count();
+
+            Some(if count == 1 {
+                VocabularySpellElement::Normal(spell)
+            } else {
+                VocabularySpellElement::Compound((spell, NonZeroUsize::new(count).unwrap()))
+            })
+        })
+        .collect::<Option<Vec<VocabularySpellElement>>>()?;
+
+    let view: String = segments
+        .iter()
+        .map(|segment| segment.view.as_str())
+        .collect();
+    let vocabulary_entry = VocabularyEntry::new(view, spells)?;
+
+    Some(ReadingSplit {
+        segments: segments.to_vec(),
+        line: serialize_vocabulary_entry(&vocabulary_entry)?,
+        estimated: segments.iter().any(|segment| segment.estimated),
+    })
+}
+
+// 読みの先頭を濁音・半濁音にしたもの（ex. はら -> ばら・ぱら）
+fn rendaku_variants(reading: &str) -> Vec<String> {
+    let mut chars = reading.chars();
+    let first = match chars.next() {
+        // うの濁音（ゔ）は連濁では現れない
+        Some('う') | None => return vec![],
+        Some(first) => first,
+    };
+    let rest: String = chars.collect();
+
+    voiced(first)
+        .into_iter()
+        .chain(semi_voiced(first))
+        .map(|first| format!("{}{}", first, rest))
+        .collect()
+}
+
+// 読みの末尾を促音にしたもの（ex. ねつ -> ねっ）
+fn sokuon_variant(reading: &str) -> Option<String> {
+    let mut chars: Vec<char> = reading.chars().collect();
+
+    if chars.len() < 2 || !matches!(chars.last(), Some('つ' | 'く' | 'ち' | 'き')) {
+        return None;
+    }
+
+    chars.pop();
+    chars.push('っ');
+
+    Some(chars.into_iter().collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::library::parse_vocabulary_entry;
+
+    fn table() -> ReadingTable {
+        let vocabulary_entries: Vec<VocabularyEntry> = [
+            "頑固:がん,こ",
+            "張る:は,る",
+            "[明日]:あした",
+            "明暗:めい,あん",
+            "熱:ねつ",
+            "帯:たい",
+        ]
+        .iter()
+        .map(|line| parse_vocabulary_entry(line).unwrap())
+        .collect();
+
+        ReadingTable::new(&vocabulary_entries)
+    }
+
+    fn lines(result: &ReadingSplitResult) -> Vec<&str> {
+        result
+            .splits
+            .iter()
+            .map(|split| split.line.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn split_reading_1() {
+        let table = table();
+
+        let result = split_reading("頑張る", "がんばる", &table).unwrap();
+        assert_eq!(lines(&result), vec!["頑張る:がん,ば,る"]);
+        assert!(!result.ambiguous);
+        assert!(!result.splits[0].estimated);
+
+        let result = split_reading("明日の熱帯", "あしたのねったい", &table).unwrap();
+        assert_eq!(lines(&result), vec!["[明日]の熱帯:あした,の,ねっ,たい"]);
+
+        let result = split_reading("タイピング", "タイピング", &table).unwrap();
+        assert_eq!(lines(&result), vec!["タイピング:た,い,ぴ,ん,ぐ"]);
+    }
+
+    #[test]
+    fn split_reading_2() {
+        let table = table();
+
+        // 辞書にない文字は推定する
+        let result = split_reading("猫", "ねこ", &table).unwrap();
+        assert_eq!(lines(&result), vec!["猫:ねこ"]);
+        assert!(result.splits[0].estimated);
+        assert!(!result.ambiguous);
+
+        // 推定する文字が続く場合には分け方が定まらない
+        let result = split_reading("猫狐", "ねこきつね", &table).unwrap();
+        assert!(result.ambiguous);
+        assert!(lines(&result).contains(&"[猫狐]:ねこきつね"));
+        assert!(lines(&result).contains(&"猫狐:ねこ,きつね"));
+
+        // 推定する文字が1文字だけなら他の文字の読みから定まる
+        let result = split_reading("猫犬", "ねこいぬ", &table).unwrap();
+        assert_eq!(lines(&result), vec!["猫犬:ねこ,いぬ"]);
+        assert!(!result.ambiguous);
+
+        // かなが読みと合わない場合には分けられない
+        let result = split_reading("頑張る", "がんばれ", &table).unwrap();
+        assert_eq!(result.splits, vec![]);
+    }
+
+    #[test]
+    fn split_reading_3() {
+        let bundled = ReadingTable::new(&vec![]);
+
+        // 辞書がなくても同梱の表から読みを引ける
+        let result = split_reading("学校", "がっこう", &bundled).unwrap();
+        assert_eq!(lines(&result), vec!["学校:がっ,こう"]);
+        assert!(!result.splits[0].estimated);
+
+        let result = split_reading("花火", "はなび", &bundled).unwrap();
+        assert_eq!(lines(&result), vec!["花火:はな,び"]);
+        assert!(!result.splits[0].estimated);
+
+        // 熟字訓は辞書から集めた読みを加えると引けるようになる
+        let result = split_reading("今日は", "きょうは", &bundled).unwrap();
+        assert!(result.splits[0].estimated);
+
+        let vocabulary_entry = parse_vocabulary_entry("[今日]:きょう").unwrap();
+        let merged = ReadingTable::new(vec![&vocabulary_entry]);
+        let result = split_reading("今日は", "きょうは", &merged).unwrap();
+        assert_eq!(lines(&result), vec!["[今日]は:きょう,は"]);
+        assert!(!result.splits[0].estimated);
+    }
+
+    #[test]
+    fn bundled_kanji_readings_1() {
+        let bundled = ReadingTable::bundled();
+
+        assert!(bundled.readings.len() > 600);
+        assert_eq!(bundled.max_key_length, 1);
+
+        for (key, readings) in &bundled.readings {
+            assert!(!key.chars().all(is_kana), "{}", key);
+
+            for reading in readings {
+                assert!(reading.chars().all(is_kana), "{} {}", key, reading);
+                assert!(SpellString::try_from(reading.clone()).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn split_reading_too_long_1() {
+        let table = table();
+
+        assert!(split_reading(&"猫".repeat(MAX_VIEW_LENGTH), "ね", &table).is_some());
+        assert_eq!(
+            split_reading(&"猫".repeat(MAX_VIEW_LENGTH + 1), "ね", &table),
+            None
+        );
+        assert_eq!(
+            split_reading("猫", &"ね".repeat(MAX_READING_LENGTH + 1), &table),
+            None
+        );
+
+        // 推定する文字だけが上限まで続いても現実的な時間で分け方を求められる
+        let result = split_reading(
+            &"猫".repeat(MAX_VIEW_LENGTH),
+            &"ねこ".repeat(MAX_READING_LENGTH / 2),
+            &table,
+        )
+        .unwrap();
+        assert!(result.ambiguous);
+        assert_eq!(result.splits.len(), MAX_PROPOSALS);
+    }
+}
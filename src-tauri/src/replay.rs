@@ -0,0 +1,430 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    fs::{create_dir_all, remove_file, File},
+    io::{self, BufReader},
+    num::NonZeroUsize,
+    path::PathBuf,
+    rc::Rc,
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::PathResolver;
+use typing_engine::{
+    DisplayInfo, QueryRequest, TypingEngine, TypingEngineError, VocabularyEntry,
+    VocabularySpellElement,
+};
+
+use crate::atomic_file::write_atomically;
+use crate::display_info::{finished_lap_count, DisplayInformation};
+use crate::ghost::Ghost;
+use crate::query_request::{QueryRequestError, QueryRequestFromUI};
+use crate::vocabulary_order::{construct_recording_order, VocabularyPicker};
+
+// 終了したタイピングのリプレイを履歴のIDごとのJSONファイルとしてアプリケーション用ディレクトリ配下に保存する
+pub(crate) struct Replays {
+    replay_dir: PathBuf,
+}
+
+impl Replays {
+    pub(crate) fn new(path_resolver: PathResolver) -> Self {
+        let mut replay_dir = path_resolver.app_dir().unwrap();
+        replay_dir.push("replay");
+
+        if !replay_dir.exists() {
+            create_dir_all(&replay_dir).unwrap();
+        }
+
+        Self { replay_dir }
+    }
+
+    pub(crate) fn save(&self, id: u64, replay: &Replay) -> io::Result<()> {
+        write_atomically(&self.replay_file_path(id), serde_json::to_vec(replay)?)
+    }
+
+    pub(crate) fn load(&self, id: u64) -> io::Result<Replay> {
+        let f = File::open(self.replay_file_path(id))?;
+
+        Ok(serde_json::from_reader(BufReader::new(f))?)
+    }
+
+    // リプレイのない履歴もあるので存在しないファイルは無視する
+    pub(crate) fn delete(&self, ids: &[u64]) -> io::Result<()> {
+        for id in ids {
+            match remove_file(self.replay_file_path(*id)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn replay_file_path(&self, id: u64) -> PathBuf {
+        self.replay_dir.join(format!("{}.json", id))
+    }
+}
+
+// 1回分のタイピングを再現するための記録
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Replay {
+    // 語彙の順番のシードや語彙区切りなどクエリの構築に使った要求
+    query_request: QueryRequestFromUI,
+    // クエリに含まれる語彙の一覧
+    vocabularies: Vec<ReplayVocabulary>,
+    // クエリに含まれる語彙を選ばれた順に`vocabularies`のインデックスで表したもの
+    picked_indices: Vec<usize>,
+    strokes: Vec<ReplayStroke>,
+}
+
+impl Replay {
+    // クエリの構築に使った語彙リストとそこから選ばれた語彙のインデックスから構築する
+    // 語彙リストのうち実際に選ばれた語彙のみを記録する
+    pub(crate) fn new(
+        query_request: QueryRequestFromUI,
+        vocabulary_entries: &[&VocabularyEntry],
+        picked_indices: &[usize],
+    ) -> Self {
+        let mut used_indices: Vec<usize> = vec![];
+        let picked_indices = picked_indices
+            .iter()
+            .map(|index| {
+                used_indices
+                    .iter()
+                    .position(|used_index| used_index == index)
+                    .unwrap_or_else(|| {
+                        used_indices.push(*index);
+                        used_indices.len() - 1
+                    })
+            })
+            .collect();
+
+        Self {
+            query_request,
+            vocabularies: used_indices
+                .iter()
+                .map(|index| vocabulary_entries[*index].into())
+                .collect(),
+            picked_indices,
+            strokes: vec![],
+        }
+    }
+
+    pub(crate) fn record(&mut self, key: char, elapsed_time_ms: usize, is_correct: bool) {
+        self.strokes.push(ReplayStroke {
+            key,
+            elapsed_time_ms,
+            is_correct,
+        });
+    }
+
+    pub(crate) fn query_request(&self) -> &QueryRequestFromUI {
+        &self.query_request
+    }
+
+    pub(crate) fn picked_indices(&self) -> &[usize] {
+        &self.picked_indices
+    }
+
+    pub(crate) fn construct_vocabulary_entries(&self) -> Result<Vec<VocabularyEntry>, ReplayError> {
+        self.vocabularies
+            .iter()
+            .map(|vocabulary| vocabulary.construct_vocabulary_entry())
+            .collect::<Option<Vec<VocabularyEntry>>>()
+            .ok_or(ReplayError::InvalidVocabulary)
+    }
+
+    // 新しいタイピングエンジンで記録した打鍵を順に再生し各打鍵後の表示情報を構築する
+    // 最初のフレームは打鍵前の状態になる
+    pub(crate) fn play(&self) -> Result<Vec<ReplayFrame>, ReplayError> {
+        // タイピングエンジン内の時刻は再生した時刻なのでラップの時間は記録した打鍵の時刻から作り直す
+        let mut lap_end_time: Vec<f64> = vec![];
+        let mut frames = vec![];
+
+        self.play_with(|elapsed_time_ms, display_info| {
+            let lap_count = finished_lap_count(display_info.key_stroke_info());
+            while lap_end_time.len() < lap_count {
+                lap_end_time.push(elapsed_time_ms as f64);
+            }
+
+            frames.push(ReplayFrame {
+                elapsed_time_ms,
+                display_info: DisplayInformation::new(display_info, lap_end_time.clone()),
+            });
+        })?;
+
+        Ok(frames)
+    }
+
+    // 記録した打鍵を再生して同じ語彙で競争するためのゴーストを構築する
+    pub(crate) fn construct_ghost(&self) -> Result<Ghost, ReplayError> {
+        let mut ghost = Ghost::new();
+
+        self.play_with(|elapsed_time_ms, display_info| ghost.push(elapsed_time_ms, display_info))?;
+
+        Ok(ghost)
+    }
+
+    // 打鍵前と各打鍵後の記録した時刻と表示情報を順に`on_frame`に渡す
+    fn play_with(&self, mut on_frame: impl FnMut(usize, &DisplayInfo)) -> Result<(), ReplayError> {
+        let vocabulary_entries = self.construct_vocabulary_entries()?;
+        let vocabulary_entries: Vec<&VocabularyEntry> = vocabulary_entries.iter().collect();
+
+        let mut typing_engine = TypingEngine::new();
+        typing_engine.init(QueryRequest::new(
+            &vocabulary_entries,
+            self.query_request.construct_quantifier()?,
+            self.query_request.construct_separator()?,
+            construct_recording_order(
+                VocabularyPicker::replaying(self.picked_indices.clone()),
+                Rc::default(),
+            ),
+        ));
+        typing_engine.start()?;
+
+        on_frame(
+            0,
+            &typing_engine
+                .construct_display_info(self.query_request.lap_request().construct_lap_request())?,
+        );
+
+        for stroke in &self.strokes {
+            let key_stroke = stroke
+                .key
+                .try_into()
+                .map_err(|_| ReplayError::InvalidKeyStroke(stroke.key))?;
+            let finished = typing_engine.stroke_key(key_stroke)?;
+
+            on_frame(
+                stroke.elapsed_time_ms,
+                &typing_engine.construct_display_info(
+                    self.query_request.lap_request().construct_lap_request(),
+                )?,
+            );
+
+            if finished {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// 語彙のシリアライズ可能な表現
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReplayVocabulary {
+    view: String,
+    spells: Vec<ReplaySpell>,
+}
+
+impl ReplayVocabulary {
+    fn construct_vocabulary_entry(&self) -> Option<VocabularyEntry> {
+        let spells = self
+            .spells
+            .iter()
+            .map(|spell| match spell {
+                ReplaySpell::Normal { spell } => spell
+                    .clone()
+                    .try_into()
+                    .ok()
+                    .map(VocabularySpellElement::Normal),
+                ReplaySpell::Compound { spell, count } => spell
+                    .clone()
+                    .try_into()
+                    .ok()
+                    .map(|spell| VocabularySpellElement::Compound((spell, *count))),
+            })
+            .collect::<Option<Vec<VocabularySpellElement>>>()?;
+
+        VocabularyEntry::new(self.view.clone(), spells)
+    }
+}
+
+impl From<&VocabularyEntry> for ReplayVocabulary {
+    fn from(vocabulary_entry: &VocabularyEntry) -> Self {
+        Self {
+            view: vocabulary_entry.view().to_string(),
+            spells: vocabulary_entry
+                .spells()
+                .iter()
+                .map(|spell| match spell {
+                    VocabularySpellElement::Normal(spell) => ReplaySpell::Normal {
+                        spell: spell.to_string(),
+                    },
+                    VocabularySpellElement::Compound((spell, count)) => ReplaySpell::Compound {
+                        spell: spell.to_string(),
+                        count: *count,
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum ReplaySpell {
+    Normal { spell: String },
+    // 表示文字列の複数文字に対応する綴り
+    Compound { spell: String, count: NonZeroUsize },
+}
+
+// 1打鍵分の記録
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReplayStroke {
+    key: char,
+    // UI側で計測したタイピング開始からの経過時間
+    elapsed_time_ms: usize,
+    is_correct: bool,
+}
+
+// 再生した1打鍵分の表示情報
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReplayFrame {
+    elapsed_time_ms: usize,
+    display_info: DisplayInformation,
+}
+
+#[derive(Debug)]
+pub(crate) enum ReplayError {
+    InvalidVocabulary,
+    InvalidKeyStroke(char),
+    InvalidQueryRequest(QueryRequestError),
+    Engine(TypingEngineError),
+}
+
+impl From<QueryRequestError> for ReplayError {
+    fn from(e: QueryRequestError) -> Self {
+        Self::InvalidQueryRequest(e)
+    }
+}
+
+impl From<TypingEngineError> for ReplayError {
+    fn from(e: TypingEngineError) -> Self {
+        Self::Engine(e)
+    }
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidVocabulary => write!(f, "replay contains invalid vocabulary"),
+            Self::InvalidKeyStroke(key) => {
+                write!(f, "replay contains invalid key stroke: {:?}", key)
+            }
+            Self::InvalidQueryRequest(e) => write!(f, "replay contains invalid query: {}", e),
+            Self::Engine(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use typing_engine::VocabularyOrder;
+
+    use super::*;
+    use crate::game_session::GameSession;
+    use crate::library::parse_vocabulary_entry;
+    use crate::query_request::LapRequestFromUI;
+
+    #[test]
+    fn play_1() {
+        let query_request: QueryRequestFromUI = serde_json::from_str(
+            r#"{"dictionaryType":"word","usedDictionaries":[],"quantifier":{"type":"vocabulary","count":3},"separator":{"type":"none"}}"#,
+        )
+        .unwrap();
+        let inu = parse_vocabulary_entry("犬:いぬ").unwrap();
+        let neko = parse_vocabulary_entry("猫:ねこ").unwrap();
+        let tori = parse_vocabulary_entry("鳥:とり").unwrap();
+
+        let mut replay = Replay::new(query_request, &[&inu, &neko, &tori], &[2, 0, 2]);
+        assert_eq!(replay.vocabularies.len(), 2);
+        assert_eq!(replay.picked_indices, vec![0, 1, 0]);
+
+        "torxiinutori".chars().enumerate().for_each(|(i, key)| {
+            replay.record(key, (i + 1) * 100, key != 'x');
+        });
+
+        let frames = replay.play().unwrap();
+
+        // 打鍵前の状態と全ての打鍵
+        assert_eq!(frames.len(), 13);
+        assert_eq!(frames[12].elapsed_time_ms, 1200);
+        assert_eq!(
+            serde_json::to_value(&frames[0].display_info).unwrap()["view"]["view"],
+            "鳥犬鳥"
+        );
+
+        let ghost = replay.construct_ghost().unwrap();
+
+        let position_at =
+            |elapsed_time_ms| serde_json::to_value(ghost.position_at(elapsed_time_ms)).unwrap();
+
+        assert_eq!(position_at(50)["keyStrokePosition"], 0);
+        // ミスタイプした打鍵では進まない
+        assert_eq!(position_at(450)["keyStrokePosition"], 3);
+        assert_eq!(position_at(550)["keyStrokePosition"], 4);
+        assert_eq!(position_at(550)["viewPosition"], 1);
+    }
+
+    #[test]
+    fn record_active_elapsed_time_1() {
+        let query_request: QueryRequestFromUI = serde_json::from_str(
+            r#"{"dictionaryType":"word","usedDictionaries":[],"quantifier":{"type":"vocabulary","count":1},"separator":{"type":"none"}}"#,
+        )
+        .unwrap();
+        let inu = parse_vocabulary_entry("犬:いぬ").unwrap();
+        let vocabulary_entries = vec![&inu];
+
+        let mut game_session = GameSession::new();
+        game_session
+            .confirm(
+                QueryRequest::new(
+                    &vocabulary_entries,
+                    query_request.construct_quantifier().unwrap(),
+                    query_request.construct_separator().unwrap(),
+                    VocabularyOrder::InOrder,
+                ),
+                LapRequestFromUI::default(),
+            )
+            .unwrap();
+        game_session.start().unwrap();
+
+        let mut replay = Replay::new(query_request, &vocabulary_entries, &[0]);
+
+        let mut stroke = |game_session: &mut GameSession, key: char| {
+            game_session.stroke_key(key.try_into().unwrap()).unwrap();
+            replay.record(
+                key,
+                game_session.active_elapsed_time().as_millis() as usize,
+                true,
+            );
+        };
+
+        stroke(&mut game_session, 'i');
+
+        game_session.pause().unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        game_session.resume().unwrap();
+
+        stroke(&mut game_session, 'n');
+
+        // 一時停止していた時間は打鍵の間隔に含まれない
+        let stroke_times: Vec<usize> = replay
+            .strokes
+            .iter()
+            .map(|stroke| stroke.elapsed_time_ms)
+            .collect();
+        assert_eq!(stroke_times.len(), 2);
+        assert!(stroke_times[1] - stroke_times[0] < 200);
+    }
+}
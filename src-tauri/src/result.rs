@@ -1,5 +1,12 @@
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
-use typing_engine::{TypingResultStatistics, TypingResultStatisticsTarget};
+use typing_engine::{
+    DisplayInfo, OnTypingStatisticsTarget, TypingResultStatistics, TypingResultStatisticsTarget,
+};
+
+use crate::scoring::{Scores, ScoringFormula};
+use crate::vocabulary_tracker::VocabularyResult;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -7,15 +14,126 @@ pub(crate) struct TypingResult {
     total_time_ms: usize,
     key_stroke: TypingResultTarget,
     ideal_key_stroke: TypingResultTarget,
+    // ラップごとの結果を記録し始める前の履歴には存在しない
+    #[serde(default)]
+    laps: Vec<LapResult>,
+    // 語彙ごとの結果を記録し始める前の履歴には存在しない
+    #[serde(default)]
+    vocabularies: Vec<VocabularyResult>,
+    // 算出方法を変えた場合にも揃うように読み込み直した時には計算し直す
+    #[serde(default)]
+    scores: TypingScores,
+    // 算出方法を選べるようになる前の履歴ではeタイピング風のスコアとする
+    #[serde(default)]
+    scoring_formula: ScoringFormula,
+    // 履歴などの保存に失敗した場合にUI側に伝えるためのもので、履歴には残さない
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    save_warnings: Vec<SaveWarning>,
 }
 
-impl From<TypingResultStatistics> for TypingResult {
-    fn from(t: TypingResultStatistics) -> Self {
-        Self {
-            total_time_ms: t.total_time().as_millis().try_into().unwrap(),
+impl TypingResult {
+    // 合計時間はタイピングエンジンの計測したものではなく一時停止していた時間を除いたものを受け取る
+    pub(crate) fn new(t: &TypingResultStatistics, total_time: Duration) -> Self {
+        let mut result = Self {
+            total_time_ms: total_time.as_millis().try_into().unwrap(),
             key_stroke: t.key_stroke().clone().into(),
             ideal_key_stroke: t.ideal_key_stroke().clone().into(),
+            laps: vec![],
+            vocabularies: vec![],
+            scores: TypingScores::default(),
+            scoring_formula: ScoringFormula::default(),
+            save_warnings: vec![],
+        };
+        result.update_scores();
+
+        result
+    }
+
+    // クエリを打ち終える前に制限時間が来た場合にそれまでに打った分から結果を構築する
+    pub(crate) fn from_time_up(display_info: &DisplayInfo, total_time_ms: usize) -> Self {
+        let key_stroke_info = display_info.key_stroke_info();
+
+        let mut result = Self {
+            total_time_ms,
+            key_stroke: key_stroke_info.on_typing_statistics().into(),
+            ideal_key_stroke: key_stroke_info.on_typing_statistics_ideal().into(),
+            laps: vec![],
+            vocabularies: vec![],
+            scores: TypingScores::default(),
+            scoring_formula: ScoringFormula::default(),
+            save_warnings: vec![],
+        };
+        result.update_scores();
+
+        result
+    }
+
+    pub(crate) fn update_scores(&mut self) {
+        // 初速は最初の語彙を打ち始められるようになってから最初の打鍵までの時間
+        let first_stroke_latency_ms = self
+            .vocabularies
+            .first()
+            .map_or(0, VocabularyResult::first_stroke_latency_ms);
+
+        self.scores = TypingScores {
+            key_stroke: Scores::new(
+                &self.key_stroke,
+                self.total_time_ms,
+                first_stroke_latency_ms,
+                self.scoring_formula,
+            ),
+            ideal_key_stroke: Scores::new(
+                &self.ideal_key_stroke,
+                self.total_time_ms,
+                first_stroke_latency_ms,
+                self.scoring_formula,
+            ),
+        };
+    }
+
+    pub(crate) fn total_time_ms(&self) -> usize {
+        self.total_time_ms
+    }
+
+    pub(crate) fn key_stroke(&self) -> &TypingResultTarget {
+        &self.key_stroke
+    }
+
+    pub(crate) fn ideal_key_stroke(&self) -> &TypingResultTarget {
+        &self.ideal_key_stroke
+    }
+
+    pub(crate) fn scores(&self) -> &TypingScores {
+        &self.scores
+    }
+
+    pub(crate) fn set_laps(&mut self, laps: Vec<LapResult>) {
+        self.laps = laps;
+    }
+
+    // 初速を使う算出方法もあるのでスコアも計算し直す
+    pub(crate) fn set_vocabularies(&mut self, vocabularies: Vec<VocabularyResult>) {
+        self.vocabularies = vocabularies;
+        self.update_scores();
+    }
+
+    pub(crate) fn set_scoring_formula(&mut self, scoring_formula: ScoringFormula) {
+        self.scoring_formula = scoring_formula;
+        self.update_scores();
+    }
+
+    pub(crate) fn set_save_warnings(&mut self, save_warnings: Vec<SaveWarning>) {
+        self.save_warnings = save_warnings;
+    }
+
+    // 理想的な打鍵列で数えた1秒あたりの打鍵数
+    // 入力方法によらずに比べるために使う
+    pub(crate) fn ideal_key_stroke_speed(&self) -> f64 {
+        if self.total_time_ms == 0 {
+            return 0.0;
         }
+
+        self.ideal_key_stroke.whole_count() as f64 * 1000.0 / self.total_time_ms as f64
     }
 }
 
@@ -27,6 +145,20 @@ pub(crate) struct TypingResultTarget {
     missed_count: usize,
 }
 
+impl TypingResultTarget {
+    pub(crate) fn whole_count(&self) -> usize {
+        self.whole_count
+    }
+
+    pub(crate) fn completely_correct_count(&self) -> usize {
+        self.completely_correct_count
+    }
+
+    pub(crate) fn missed_count(&self) -> usize {
+        self.missed_count
+    }
+}
+
 impl From<TypingResultStatisticsTarget> for TypingResultTarget {
     fn from(t: TypingResultStatisticsTarget) -> Self {
         Self {
@@ -36,3 +168,56 @@ impl From<TypingResultStatisticsTarget> for TypingResultTarget {
         }
     }
 }
+
+impl From<&OnTypingStatisticsTarget> for TypingResultTarget {
+    fn from(t: &OnTypingStatisticsTarget) -> Self {
+        Self {
+            whole_count: t.finished_count(),
+            completely_correct_count: t.completely_correct_count(),
+            missed_count: t.wrong_count(),
+        }
+    }
+}
+
+// 字数の数え方ごとの指標
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TypingScores {
+    // 実際にタイプしたローマ字系列の字数で算出したもの
+    key_stroke: Scores,
+    // タイプ数が最も少なくなるようなローマ字系列の字数で算出したもの
+    ideal_key_stroke: Scores,
+}
+
+impl TypingScores {
+    pub(crate) fn key_stroke(&self) -> &Scores {
+        &self.key_stroke
+    }
+}
+
+// タイピング自体は終了したが保存に失敗したもの
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "target", rename_all = "camelCase")]
+pub(crate) enum SaveWarning {
+    History { message: String },
+    Replay { message: String },
+}
+
+// 1ラップ分の結果
+// 最後のラップは途中で終了していることもある
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LapResult {
+    // 直前のラップ末からこのラップ末までの時間
+    time_ms: usize,
+    miss_count: usize,
+}
+
+impl LapResult {
+    pub(crate) fn new(time_ms: usize, miss_count: usize) -> Self {
+        Self {
+            time_ms,
+            miss_count,
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use crate::vocabulary_tracker::VocabularyResult;
+
+// 過去の語彙ごとの結果から語彙の表示文字列ごとの苦手度を求める
+//
+// 苦手度は以下の2つの和とする
+// * ミスタイプ率（ミスタイプ数 / 打鍵数）
+// * 1打鍵あたりの時間が全語彙の平均に比べてどれだけ遅いか（平均より速い場合は0）
+// 苦手度が0の語彙は復習の対象にならない
+pub(crate) fn construct_weakness_scores<'a>(
+    results: impl Iterator<Item = &'a VocabularyResult>,
+) -> HashMap<String, f64> {
+    // 表示文字列ごとの（打鍵数, ミスタイプ数, 打ち終えた回の打鍵数, 打ち終えた回の時間）
+    let mut aggregated: HashMap<&str, (usize, usize, usize, usize)> = HashMap::new();
+
+    results.for_each(|result| {
+        let entry = aggregated.entry(result.view()).or_default();
+        entry.0 += result.stroke_count();
+        entry.1 += result.miss_count();
+
+        if let Some(time_ms) = result.time_ms() {
+            entry.2 += result.stroke_count();
+            entry.3 += time_ms;
+        }
+    });
+
+    let (timed_stroke_count, timed_ms) =
+        aggregated.values().fold((0, 0), |(strokes, ms), entry| {
+            (strokes + entry.2, ms + entry.3)
+        });
+
+    let average_ms_per_stroke = if timed_stroke_count == 0 {
+        None
+    } else {
+        Some(timed_ms as f64 / timed_stroke_count as f64)
+    };
+
+    aggregated
+        .into_iter()
+        .filter(|(_, (stroke_count, ..))| *stroke_count != 0)
+        .map(
+            |(view, (stroke_count, miss_count, timed_stroke_count, timed_ms))| {
+                let miss_rate = miss_count as f64 / stroke_count as f64;
+
+                let slowness = match average_ms_per_stroke {
+                    Some(average) if timed_stroke_count != 0 && average > 0.0 => {
+                        let ms_per_stroke = timed_ms as f64 / timed_stroke_count as f64;
+                        (ms_per_stroke / average - 1.0).max(0.0)
+                    }
+                    _ => 0.0,
+                };
+
+                (view.to_string(), miss_rate + slowness)
+            },
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn construct_weakness_scores_1() {
+        let results: Vec<VocabularyResult> = serde_json::from_str(
+            r#"[
+                {"view":"犬","strokeCount":4,"missCount":1,"timeMs":400,"firstStrokeLatencyMs":0},
+                {"view":"猫","strokeCount":4,"missCount":0,"timeMs":1200,"firstStrokeLatencyMs":0},
+                {"view":"鳥","strokeCount":4,"missCount":0,"timeMs":400,"firstStrokeLatencyMs":0}
+            ]"#,
+        )
+        .unwrap();
+
+        let scores = construct_weakness_scores(results.iter());
+
+        // 平均は1打鍵あたり2000/12ms
+        assert_eq!(scores["犬"], 0.25);
+        assert_eq!(scores["猫"], 0.8);
+        assert_eq!(scores["鳥"], 0.0);
+    }
+}
@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+
+use crate::result::TypingResultTarget;
+
+// 1単語とみなす字数
+const CHARACTERS_PER_WORD: f64 = 5.0;
+
+// 結果の主なスコアとして使う算出方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ScoringFormula {
+    // eタイピング風のスコア
+    // 1分あたりの字数 x ( 正確率 )^3 の小数点以下切り捨て
+    ETyping,
+    // タイプウェル風のRKPM
+    // 初速（最初の打鍵までの時間）とその打鍵を除いた1分あたりの打鍵数の小数点以下第2位を四捨五入
+    TypewellRkpm,
+    // 5字を1単語とした1分あたりの単語数からミスタイプ1回につき1単語を引いたもの
+    NetWpm,
+}
+
+impl Default for ScoringFormula {
+    fn default() -> Self {
+        Self::ETyping
+    }
+}
+
+// 結果から算出する各種の指標
+// 結果画面・履歴・書き出しで同じ値になるようにここでのみ計算する
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Scores {
+    // 1分あたりの字数（小数点以下切り捨て）
+    cpm: usize,
+    // 1回もミスタイプしないで打てた字数の割合（百分率）
+    accuracy: f64,
+    formula: ScoringFormula,
+    // formulaで算出したスコア
+    score: f64,
+}
+
+impl Scores {
+    // 字数の数え方ごとの統計と一時停止していた時間を除いた合計時間から算出する
+    pub(crate) fn new(
+        target: &TypingResultTarget,
+        total_time_ms: usize,
+        first_stroke_latency_ms: usize,
+        formula: ScoringFormula,
+    ) -> Self {
+        let cpm = cpm(target, total_time_ms);
+        let accuracy = accuracy(target);
+
+        let score = match formula {
+            ScoringFormula::ETyping => e_typing_score(cpm, accuracy),
+            ScoringFormula::TypewellRkpm => {
+                typewell_rkpm(target, total_time_ms, first_stroke_latency_ms)
+            }
+            ScoringFormula::NetWpm => net_wpm(target, total_time_ms),
+        };
+
+        Self {
+            cpm,
+            accuracy,
+            formula,
+            score,
+        }
+    }
+
+    pub(crate) fn cpm(&self) -> usize {
+        self.cpm
+    }
+
+    pub(crate) fn accuracy(&self) -> f64 {
+        self.accuracy
+    }
+
+    pub(crate) fn formula(&self) -> ScoringFormula {
+        self.formula
+    }
+
+    pub(crate) fn score(&self) -> f64 {
+        self.score
+    }
+}
+
+fn accuracy(target: &TypingResultTarget) -> f64 {
+    if target.whole_count() == 0 {
+        return 0.0;
+    }
+
+    target.completely_correct_count() as f64 / target.whole_count() as f64 * 100.0
+}
+
+fn cpm(target: &TypingResultTarget, total_time_ms: usize) -> usize {
+    if total_time_ms == 0 {
+        return 0;
+    }
+
+    target.whole_count() * 60000 / total_time_ms
+}
+
+fn e_typing_score(cpm: usize, accuracy: f64) -> f64 {
+    (cpm as f64 * (accuracy / 100.0).powi(3)).floor()
+}
+
+// ミスタイプした打鍵は字数に含まれないのでそのまま数える
+fn typewell_rkpm(
+    target: &TypingResultTarget,
+    total_time_ms: usize,
+    first_stroke_latency_ms: usize,
+) -> f64 {
+    let time_ms = total_time_ms.saturating_sub(first_stroke_latency_ms);
+    if target.whole_count() == 0 || time_ms == 0 {
+        return 0.0;
+    }
+
+    let key_stroke_count = (target.whole_count() - 1) as f64;
+
+    (key_stroke_count * 60000.0 / time_ms as f64 * 10.0).round() / 10.0
+}
+
+// ミスタイプが多い場合にも負にはしない
+fn net_wpm(target: &TypingResultTarget, total_time_ms: usize) -> f64 {
+    if total_time_ms == 0 {
+        return 0.0;
+    }
+
+    let minutes = total_time_ms as f64 / 60000.0;
+    let gross_words = target.whole_count() as f64 / CHARACTERS_PER_WORD;
+
+    ((gross_words - target.missed_count() as f64) / minutes).max(0.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn target(
+        whole_count: usize,
+        completely_correct_count: usize,
+        missed_count: usize,
+    ) -> TypingResultTarget {
+        serde_json::from_value(serde_json::json!({
+            "wholeCount": whole_count,
+            "completelyCorrectCount": completely_correct_count,
+            "missedCount": missed_count,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn scores_1() {
+        let target = target(301, 270, 40);
+
+        let e_typing = Scores::new(&target, 60000, 1000, ScoringFormula::ETyping);
+        assert_eq!(e_typing.cpm(), 301);
+        assert_eq!(e_typing.accuracy(), 270.0 / 301.0 * 100.0);
+        assert_eq!(e_typing.formula(), ScoringFormula::ETyping);
+        assert_eq!(e_typing.score(), 217.0);
+
+        // 初速の1秒と最初の1打鍵を除いて59秒で300打鍵
+        let typewell = Scores::new(&target, 60000, 1000, ScoringFormula::TypewellRkpm);
+        assert_eq!(typewell.cpm(), 301);
+        assert_eq!(typewell.score(), 305.1);
+
+        let net_wpm = Scores::new(&target, 60000, 1000, ScoringFormula::NetWpm);
+        assert_eq!(net_wpm.score(), 301.0 / 5.0 - 40.0);
+    }
+
+    #[test]
+    fn scores_zero_time_1() {
+        for formula in [
+            ScoringFormula::ETyping,
+            ScoringFormula::TypewellRkpm,
+            ScoringFormula::NetWpm,
+        ] {
+            let scores = Scores::new(&target(0, 0, 0), 0, 0, formula);
+
+            assert_eq!(scores.cpm(), 0);
+            assert_eq!(scores.accuracy(), 0.0);
+            assert_eq!(scores.score(), 0.0);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use rand::{
+    distributions::WeightedIndex, prelude::Distribution, random, rngs::StdRng, seq::SliceRandom,
+    thread_rng, SeedableRng,
+};
+use typing_engine::VocabularyOrder;
+
+// 語彙リストから次の語彙を選ぶ方法
+pub(crate) enum VocabularyPicker {
+    // 一様ランダムに選ぶ
+    Random,
+    // 語彙リストの各語彙に与えられた重みに比例した確率で選ぶ
+    Weighted(WeightedIndex<f64>),
+    // 語彙リストの順に選ぶ
+    InOrder,
+    // 語彙リストの逆順に選ぶ
+    Reversed,
+    // 並べ替えた語彙のインデックスの順に選ぶ
+    // 2つ目の要素は語彙のインデックスから並べ替えた後の位置を引くためのもの
+    Shuffled(Vec<usize>, Vec<usize>),
+    // 記録しておいた語彙のインデックスの順に選ぶ
+    // 記録を使い切った場合には最初から繰り返す
+    Replaying(Vec<usize>, Cell<usize>),
+}
+
+impl VocabularyPicker {
+    // 重みが全て0だったり不正な値を含む場合には一様ランダムにする
+    pub(crate) fn weighted(weights: &[f64]) -> Self {
+        WeightedIndex::new(weights).map_or(Self::Random, Self::Weighted)
+    }
+
+    // 同じシードからは常に同じ順番になる
+    pub(crate) fn seeded_shuffle(seed: u64, vocabulary_count: usize) -> Self {
+        let mut order: Vec<usize> = (0..vocabulary_count).collect();
+        order.shuffle(&mut StdRng::seed_from_u64(seed));
+
+        let mut position_of_index = vec![0; vocabulary_count];
+        order
+            .iter()
+            .enumerate()
+            .for_each(|(position, index)| position_of_index[*index] = position);
+
+        Self::Shuffled(order, position_of_index)
+    }
+
+    pub(crate) fn replaying(picked_indices: Vec<usize>) -> Self {
+        Self::Replaying(picked_indices, Cell::new(0))
+    }
+
+    fn pick(&self, prev_index: &Option<usize>, vocabulary_count: usize) -> usize {
+        match self {
+            Self::Random => random::<usize>() % vocabulary_count,
+            Self::Weighted(weighted_index) => weighted_index.sample(&mut thread_rng()),
+            Self::InOrder => prev_index.map_or(0, |prev| (prev + 1) % vocabulary_count),
+            Self::Reversed => prev_index.map_or(vocabulary_count - 1, |prev| {
+                (prev + vocabulary_count - 1) % vocabulary_count
+            }),
+            Self::Shuffled(order, position_of_index) => {
+                let position =
+                    prev_index.map_or(0, |prev| (position_of_index[prev] + 1) % vocabulary_count);
+
+                order[position]
+            }
+            Self::Replaying(picked_indices, position) => {
+                let index = if picked_indices.is_empty() {
+                    0
+                } else {
+                    picked_indices[position.get() % picked_indices.len()]
+                };
+                position.set(position.get() + 1);
+
+                index % vocabulary_count
+            }
+        }
+    }
+}
+
+// 選んだ語彙のインデックスを選んだ順に`picked_indices`へ記録するVocabularyOrderを構築する
+// クエリの構築後に記録を見ればクエリにどの語彙がどの順番で含まれているかが分かる
+pub(crate) fn construct_recording_order(
+    picker: VocabularyPicker,
+    picked_indices: Rc<RefCell<Vec<usize>>>,
+) -> VocabularyOrder {
+    VocabularyOrder::Arbitrary(Box::new(move |prev_index, vocabulary_entries| {
+        let index = picker.pick(prev_index, vocabulary_entries.len());
+        picked_indices.borrow_mut().push(index);
+
+        index
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pick_sequence(picker: &VocabularyPicker, count: usize, length: usize) -> Vec<usize> {
+        let mut prev_index = None;
+
+        (0..length)
+            .map(|_| {
+                let index = picker.pick(&prev_index, count);
+                prev_index.replace(index);
+                index
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pick_in_order_1() {
+        assert_eq!(
+            pick_sequence(&VocabularyPicker::InOrder, 3, 5),
+            vec![0, 1, 2, 0, 1]
+        );
+    }
+
+    #[test]
+    fn pick_reversed_1() {
+        assert_eq!(
+            pick_sequence(&VocabularyPicker::Reversed, 3, 5),
+            vec![2, 1, 0, 2, 1]
+        );
+    }
+
+    #[test]
+    fn pick_seeded_shuffle_1() {
+        let first = pick_sequence(&VocabularyPicker::seeded_shuffle(42, 10), 10, 20);
+        let second = pick_sequence(&VocabularyPicker::seeded_shuffle(42, 10), 10, 20);
+
+        assert_eq!(first, second);
+
+        // 1周の間に全ての語彙が1回ずつ選ばれる
+        let mut one_round = first[..10].to_vec();
+        one_round.sort_unstable();
+        assert_eq!(one_round, (0..10).collect::<Vec<usize>>());
+        assert_eq!(first[..10], first[10..]);
+    }
+}
@@ -0,0 +1,254 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+// クエリを構成する語彙ごとにタイピングの様子を記録する
+// 語彙区切りも1つの語彙として扱うが結果には含めない
+#[derive(Debug, Clone, Default)]
+pub(crate) struct VocabularyTracker {
+    vocabularies: Vec<TrackedVocabulary>,
+    // 直前に打ち終えた語彙の打ち終えた時刻
+    last_completed_time_ms: usize,
+}
+
+impl VocabularyTracker {
+    // クエリに選ばれた順に並べた語彙の表示文字列と語彙区切りかどうかから構築する
+    pub(crate) fn new(sequence: Vec<(String, bool)>) -> Self {
+        let mut position = 0;
+
+        let vocabularies = sequence
+            .into_iter()
+            .map(|(view, is_separator)| {
+                let view_length = view.chars().count();
+                let view_range = position..position + view_length;
+                position += view_length;
+
+                TrackedVocabulary::new(view, is_separator, view_range)
+            })
+            .collect();
+
+        Self {
+            vocabularies,
+            last_completed_time_ms: 0,
+        }
+    }
+
+    // 1打鍵分を記録する
+    // 位置は打鍵前後の表示文字列上のカーソル位置で打ち終えた後の位置はNoneとする
+    pub(crate) fn record(
+        &mut self,
+        prev_position: usize,
+        next_position: Option<usize>,
+        key: char,
+        is_correct: bool,
+        elapsed_time_ms: usize,
+    ) {
+        let prev_index = match self.index_of_position(prev_position) {
+            Some(i) => i,
+            None => return,
+        };
+
+        let vocabulary = &mut self.vocabularies[prev_index];
+        vocabulary.stroke_count += 1;
+        if is_correct {
+            vocabulary.key_stroke.push(key);
+        } else {
+            vocabulary.miss_count += 1;
+        }
+        if vocabulary.first_stroke_time_ms.is_none() {
+            vocabulary.first_stroke_time_ms.replace(elapsed_time_ms);
+            vocabulary
+                .started_time_ms
+                .replace(self.last_completed_time_ms);
+        }
+
+        // カーソルが語彙を越えたらその間の語彙は打ち終えたことになる
+        let next_index = next_position
+            .map_or(Some(self.vocabularies.len()), |p| self.index_of_position(p))
+            .unwrap_or(self.vocabularies.len());
+
+        if next_index > prev_index {
+            for vocabulary in &mut self.vocabularies[prev_index..next_index] {
+                vocabulary
+                    .started_time_ms
+                    .get_or_insert(self.last_completed_time_ms);
+                vocabulary.completed_time_ms.replace(elapsed_time_ms);
+            }
+
+            self.last_completed_time_ms = elapsed_time_ms;
+        }
+    }
+
+    // 打ち始めた語彙ごとの結果を構築する
+    pub(crate) fn construct_results(&self) -> Vec<VocabularyResult> {
+        self.vocabularies
+            .iter()
+            .filter(|vocabulary| !vocabulary.is_separator)
+            .filter_map(|vocabulary| vocabulary.construct_result())
+            .collect()
+    }
+
+    fn index_of_position(&self, position: usize) -> Option<usize> {
+        self.vocabularies
+            .iter()
+            .position(|vocabulary| vocabulary.view_range.contains(&position))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TrackedVocabulary {
+    view: String,
+    is_separator: bool,
+    // クエリ全体の表示文字列におけるこの語彙の範囲
+    view_range: Range<usize>,
+    key_stroke: String,
+    stroke_count: usize,
+    miss_count: usize,
+    // この語彙を打ち始められるようになった時刻（直前の語彙を打ち終えた時刻）
+    started_time_ms: Option<usize>,
+    first_stroke_time_ms: Option<usize>,
+    completed_time_ms: Option<usize>,
+}
+
+impl TrackedVocabulary {
+    fn new(view: String, is_separator: bool, view_range: Range<usize>) -> Self {
+        Self {
+            view,
+            is_separator,
+            view_range,
+            key_stroke: String::new(),
+            stroke_count: 0,
+            miss_count: 0,
+            started_time_ms: None,
+            first_stroke_time_ms: None,
+            completed_time_ms: None,
+        }
+    }
+
+    fn construct_result(&self) -> Option<VocabularyResult> {
+        let started_time_ms = self.started_time_ms?;
+        let first_stroke_time_ms = self.first_stroke_time_ms?;
+
+        Some(VocabularyResult {
+            view: self.view.clone(),
+            key_stroke: self.key_stroke.clone(),
+            stroke_count: self.stroke_count,
+            miss_count: self.miss_count,
+            time_ms: self
+                .completed_time_ms
+                .map(|completed| completed.saturating_sub(started_time_ms)),
+            first_stroke_latency_ms: first_stroke_time_ms.saturating_sub(started_time_ms),
+        })
+    }
+}
+
+// 1語彙分のタイピングの結果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct VocabularyResult {
+    view: String,
+    // ミスタイプを除いて実際に打った打鍵列で、同じ綴りでも打ち方によって異なる
+    // 語彙ごとの結果に打鍵列を記録し始める前の履歴には存在しない
+    #[serde(default)]
+    key_stroke: String,
+    // ミスタイプを含む打鍵数
+    stroke_count: usize,
+    miss_count: usize,
+    // 打ち始められるようになってから打ち終えるまでの時間
+    // 打ち終える前にタイピングが終了した場合にはNone
+    time_ms: Option<usize>,
+    // 打ち始められるようになってから最初の打鍵までの時間
+    first_stroke_latency_ms: usize,
+}
+
+impl VocabularyResult {
+    pub(crate) fn view(&self) -> &str {
+        &self.view
+    }
+
+    pub(crate) fn stroke_count(&self) -> usize {
+        self.stroke_count
+    }
+
+    pub(crate) fn miss_count(&self) -> usize {
+        self.miss_count
+    }
+
+    pub(crate) fn time_ms(&self) -> Option<usize> {
+        self.time_ms
+    }
+
+    pub(crate) fn first_stroke_latency_ms(&self) -> usize {
+        self.first_stroke_latency_ms
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn construct_results_1() {
+        let mut tracker = VocabularyTracker::new(vec![
+            ("犬".to_string(), false),
+            (" ".to_string(), true),
+            ("猫".to_string(), false),
+        ]);
+
+        // 犬: i, ミスタイプ, n, u
+        tracker.record(0, Some(0), 'i', true, 100);
+        tracker.record(0, Some(0), 'x', false, 200);
+        tracker.record(0, Some(0), 'n', true, 300);
+        tracker.record(0, Some(1), 'u', true, 400);
+        // 語彙区切り
+        tracker.record(1, Some(2), ' ', true, 500);
+        // 猫: 打ち終える
+        tracker.record(2, Some(2), 'n', true, 900);
+        tracker.record(2, Some(2), 'e', true, 950);
+        tracker.record(2, Some(2), 'k', true, 980);
+        tracker.record(2, None, 'o', true, 1000);
+
+        assert_eq!(
+            tracker.construct_results(),
+            vec![
+                VocabularyResult {
+                    view: "犬".to_string(),
+                    key_stroke: "inu".to_string(),
+                    stroke_count: 4,
+                    miss_count: 1,
+                    time_ms: Some(400),
+                    first_stroke_latency_ms: 100,
+                },
+                VocabularyResult {
+                    view: "猫".to_string(),
+                    key_stroke: "neko".to_string(),
+                    stroke_count: 4,
+                    miss_count: 0,
+                    time_ms: Some(500),
+                    first_stroke_latency_ms: 400,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn construct_results_2() {
+        let mut tracker =
+            VocabularyTracker::new(vec![("寿司".to_string(), false), ("猫".to_string(), false)]);
+
+        // 寿司: 「し」をshiではなくsiで打っても打った通りに記録する
+        tracker.record(0, Some(0), 's', true, 100);
+        tracker.record(0, Some(1), 'u', true, 200);
+        tracker.record(1, Some(1), 's', true, 300);
+        tracker.record(1, Some(2), 'i', true, 400);
+        // 猫: 途中で終了
+        tracker.record(2, Some(2), 'n', true, 500);
+
+        let results = tracker.construct_results();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].key_stroke, "susi");
+        assert_eq!(results[1].key_stroke, "n");
+        assert_eq!(results[1].time_ms(), None);
+        assert_eq!(results[1].first_stroke_latency_ms(), 100);
+    }
+}
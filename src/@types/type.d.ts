@@ -10,18 +10,95 @@ type DictionaryType = 'word' | 'sentence';
 type DictionaryOrigin = 'user_defined' | 'builtin';
 
 type DictionaryInfo = {
+  // ライブラリディレクトリからの相対パス（ex. business/IT用語）
   name: string,
+  // 辞書の置かれたサブディレクトリ（ex. business）で直下の場合には空文字列
+  category: string,
   type: DictionaryType,
   origin: DictionaryOrigin,
-  invalidLineNumberList: number[],
+  metadata: DictionaryMetadata,
+  // 語彙に付けられているタグの一覧
+  tags: string[],
+  diagnostics: ParseDiagnostic[],
+  // 綴りを置き換えたことなど行を無効にはしない通知
+  notices: ParseDiagnostic[],
   validVocabularyCount: number,
 }
 
+// 辞書ファイルのヘッダーに書かれた情報で書かれていない項目はnull
+type DictionaryMetadata = {
+  title: string | null,
+  author: string | null,
+  description: string | null,
+  license: string | null,
+  language: string | null,
+  version: string | null,
+}
+
+type ParseDiagnosticReason =
+  { kind: 'missing_colon' }
+  | { kind: 'extra_colon' }
+  | { kind: 'nested_square_parentheses' }
+  | { kind: 'unmatched_closing_square_parenthesis' }
+  | { kind: 'unclosed_square_parenthesis' }
+  | { kind: 'empty_square_parentheses' }
+  | { kind: 'spell_count_mismatch', view_segment_count: number, spell_count: number }
+  | { kind: 'invalid_spell', spell: string, invalid_char: string }
+  | { kind: 'invalid_vocabulary' }
+  | { kind: 'unclosed_header' }
+  | { kind: 'unknown_header_key', key: string }
+  | { kind: 'invalid_tag', tag: string }
+  // 綴りを置き換えたことを表し、この行自体は有効
+  | { kind: 'normalized_spell', spell: string, normalized: string }
+  // コメントがタグのように見えることを表し、この行自体は有効
+  | { kind: 'tag_like_comment', tags: string[] };
+
+// includeが空でなければそのいずれかのタグを持つ語彙だけを使い、excludeのいずれかのタグを持つ語彙は使わない
+type TagFilter = {
+  include: string[],
+  exclude: string[],
+}
+
+type ParseDiagnostic = {
+  line_number: number,
+  column_start: number,
+  column_end: number,
+  text: string,
+  reason: ParseDiagnosticReason,
+}
+
+// 語彙と読み全体から推定した綴りの分け方
+type ReadingSplit = {
+  segments: { view: string, spell: string, estimated: boolean }[],
+  // そのまま辞書に書ける1行
+  line: string,
+  // 辞書から読みを引けずに推定したまとまりを含む
+  estimated: boolean,
+}
+
+type ReadingSplitResult = {
+  splits: ReadingSplit[],
+  // 同じくらい尤もらしい分け方が複数ある
+  ambiguous: boolean,
+}
+
+type ImportConflictResolution = 'rename' | 'overwrite' | 'skip';
+
+type ImportDictionaryResult = {
+  // 取り込んだユーザー定義辞書の辞書名
+  name: string,
+  action: 'imported' | 'renamed' | 'overwritten' | 'skipped',
+  validVocabularyCount: number,
+  diagnostics: ParseDiagnostic[],
+  notices: ParseDiagnostic[],
+}
+
 type Library = {
   readonly usedDictionaries: [DictionaryOrigin, string][],
   readonly availableDictionaries: DictionaryInfo[],
   readonly usedDictionaryType: DictionaryType,
   readonly keyStrokeCountThreshold: number,
+  readonly scoringFormula: ScoringFormula,
 }
 
 type LibraryOperatorActionType =
@@ -30,6 +107,7 @@ type LibraryOperatorActionType =
   | { type: 'load' }
   | { type: 'type', dictionaryType: DictionaryType }
   | { type: 'keyStrokeCountThreshold', keyStrokeCountThreshold: number }
+  | { type: 'scoringFormula', scoringFormula: ScoringFormula }
   | { type: 'confirmQuery' };
 
 
@@ -49,9 +127,16 @@ type KeyStrokeDisplayInfo = {
   readonly lapEndTime: number[],
 }
 
+// 同じ経過時間でのゴーストの表示文字列と打鍵列それぞれの上での位置
+type GhostPosition = {
+  readonly viewPosition: number,
+  readonly keyStrokePosition: number,
+}
+
 type DisplayInfo = {
   readonly view: ViewDisplayInfo,
   readonly keyStroke: KeyStrokeDisplayInfo,
+  readonly ghost: GhostPosition | null,
 }
 
 type CharacterStyleInformation = {
@@ -71,6 +156,50 @@ type TypingResultStatistics = {
   keyStroke: TypingResultStatisticsTarget,
   idealKeyStroke: TypingResultStatisticsTarget,
   totalTimeMs: number,
+  laps: LapResult[],
+  vocabularies: VocabularyResult[],
+  scores: TypingScores,
+  // 履歴などの保存に失敗した場合にだけ存在する
+  saveWarnings?: SaveWarning[],
+}
+
+// タイピング自体は終了したが保存に失敗したもの
+type SaveWarning = {
+  target: 'history' | 'replay',
+  message: string,
+}
+
+// 字数の数え方ごとの指標
+type TypingScores = {
+  keyStroke: Scores,
+  idealKeyStroke: Scores,
+}
+
+// 結果の主なスコアの算出方法
+type ScoringFormula = 'eTyping' | 'typewellRkpm' | 'netWpm';
+
+type Scores = {
+  // 1分あたりの字数
+  cpm: number,
+  accuracy: number,
+  formula: ScoringFormula,
+  score: number,
+}
+
+// 最後のラップは途中で終了していることもある
+type LapResult = {
+  timeMs: number,
+  missCount: number,
+}
+
+type VocabularyResult = {
+  view: string,
+  spell: string,
+  strokeCount: number,
+  missCount: number,
+  // 打ち終える前にタイピングが終了した場合にはnull
+  timeMs: number | null,
+  firstStrokeLatencyMs: number,
 }
 
 type TypingResultStatisticsTarget = {
@@ -78,3 +207,9 @@ type TypingResultStatisticsTarget = {
   completelyCorrectCount: number,
   missedCount: number,
 }
+
+// コマンドが失敗した時にバックエンドから返されるエラー
+type ToUIError = {
+  readonly kind: 'invalidGameState' | 'dictionaryNotFound' | 'invalidQueryRequest' | 'invalidKeyInput' | 'invalidReplay' | 'invalidDictionary' | 'invalidDictionaryEdit' | 'io' | 'eventEmission',
+  readonly message: string,
+}
@@ -11,7 +11,7 @@ export function ModeSelectView() {
   const gameStateContext = useContext(GameStateContext);
 
   // NOTE: 分割代入を使っていこう cf. <https://developer.mozilla.org/ja/docs/Web/JavaScript/Reference/Operators/Destructuring_assignment>
-  const { library: { usedDictionaryType, usedDictionaries: usedDictionaries, availableDictionaries, keyStrokeCountThreshold }, libraryOperator } = useContext(LibraryContext);
+  const { library: { usedDictionaryType, usedDictionaries: usedDictionaries, availableDictionaries, keyStrokeCountThreshold, scoringFormula }, libraryOperator } = useContext(LibraryContext);
 
   const canStart = () => {
     return usedDictionaries.length !== 0;
@@ -44,6 +44,8 @@ export function ModeSelectView() {
   const WORD_TOOLTIP_TEXT = `辞書（.tconciergew形式のファイル）に含まれる単語からいくつかランダムに選びます。\n文章との併用はできません。`;
   const SENTENCE_TOOLTIP_TEXT = `辞書（.tconcierges形式のファイル）に含まれる文章からランダムに選びます。\n単語との併用はできません。`
 
+  const SCORING_FORMULA_TOOLTIP_TEXT = '結果画面で大きく表示するスコアの算出方法です。\nスコア:1分あたりの字数 x 正確率^3\nRKPM:最初の打鍵までの時間を除いた1分あたりの打鍵数\nネットWPM:5字を1単語とした1分あたりの単語数からミスタイプ1回につき1単語を引いたもの';
+
   const KEY_STROKE_THRESHOLD_TOOLTIP_TEXT = 'ローマ字を何文字打ったらゲームが終了するかというタイプ数です。\n平均的な人だと1分間に150から250タイプできるとされているので、1分間のゲームをしたい場合にはこれくらいの値にすると良いです。';
 
   return (
@@ -89,6 +91,17 @@ export function ModeSelectView() {
             : undefined
         }
 
+        <div className='row d-flex justify-content-center mt-2'>
+          <div className='d-flex justify-content-center align-items-center'>
+            <select className='form-select form-select-sm w-auto' value={scoringFormula} onChange={e => libraryOperator({ type: 'scoringFormula', scoringFormula: e.target.value as ScoringFormula })}>
+              <option value='eTyping'>スコア</option>
+              <option value='typewellRkpm'>RKPM</option>
+              <option value='netWpm'>ネットWPM</option>
+            </select>
+            <i className='bi bi-question-circle ms-1' data-bs-toggle='tooltip' data-bs-placement='top' title={SCORING_FORMULA_TOOLTIP_TEXT} />
+          </div>
+        </div>
+
         <div className='row d-flex justify-content-center mt-3'>
           <div className='col-6 d-flex justify-content-center'>
             <button onClick={confirmReady} className='btn btn-lg btn-primary' disabled={!canStart()}>Start</button>
@@ -1,5 +1,12 @@
 import _, { useState } from 'react';
 
+// スコアの算出方法ごとの表示名
+const SCORING_FORMULA_TITLE: { [formula in ScoringFormula]: string } = {
+  eTyping: 'スコア',
+  typewellRkpm: 'RKPM',
+  netWpm: 'ネットWPM',
+};
+
 function SubContent(props: { title: string, content: string }): JSX.Element {
   return (
     <div className='w-100 h-100 d-flex flex-column align-items-center lh-sm'>
@@ -14,21 +21,20 @@ export function ResultSummaryPane(props: { summary: TypingResultStatistics }): J
 
   const summary = props.summary;
   const effectiveKeyStroke = isWordCountIdeal ? summary.idealKeyStroke : summary.keyStroke;
+  // 各指標はバックエンドで計算したものを使い履歴や書き出しと揃える
+  const scores = isWordCountIdeal ? summary.scores.idealKeyStroke : summary.scores.keyStroke;
 
   const wordCount = effectiveKeyStroke.wholeCount;
-  const accuracy = wordCount == 0 ? 0 : effectiveKeyStroke.completelyCorrectCount * 1.0 / wordCount * 100;
-
-  // WPMは切り捨て
-  const wpm = summary.totalTimeMs == 0 ? 0 : Math.floor(wordCount * 60000 / summary.totalTimeMs);
+  const accuracy = scores.accuracy;
+  const cpm = scores.cpm;
 
-  // WPM x ( 正確率 )^3 の小数点以下切り捨て
-  // 実際のeタイピングはwordCountとしてidealじゃなくて実際の打ったローマ字数を使っている
-  const eTypingScore = Math.floor(wpm * (accuracy / 100) ** 3);
+  // 算出方法はタイピングを始める前に選んだもので、バックエンドで計算したスコアをそのまま表示する
+  const score = scores.formula === 'eTyping' ? scores.score.toString() : scores.score.toFixed(1);
 
   const WORD_COUNT_IDEAL_HELP = 'オン:タイプ数が最も少なくなるようなローマ字系列の字数で計算します\
   \nオフ:実際にタイプしたローマ字系列の字数で計算します\
   \nEx. 「きょう」を「kilyou」と打った場合にはオンにすると4字（kyou）、オフにすると6字打ったことになります\
-  \nオンにすると実際にタイプした文字数よりも少なくなるのでWPM・スコアは低くなります';
+  \nオンにすると実際にタイプした文字数よりも少なくなるのでCPM・スコアは低くなります';
 
 
   return (
@@ -46,8 +52,8 @@ export function ResultSummaryPane(props: { summary: TypingResultStatistics }): J
       <div className='flex-grow-1'>
         <div className='d-flex flex-column w-100 h-100'>
           <div className='d-flex flex-column w-100 h-100 justify-content-end align-items-center lh-1'>
-            <div className='text-secondary'>スコア</div>
-            <div className='text-primary display-1'>{eTypingScore}</div>
+            <div className='text-secondary'>{SCORING_FORMULA_TITLE[scores.formula]}</div>
+            <div className='text-primary display-1'>{score}</div>
           </div>
 
           <div className='d-flex flex-column w-100 h-100 justify-content-center align-items-center lh-1'>
@@ -58,7 +64,7 @@ export function ResultSummaryPane(props: { summary: TypingResultStatistics }): J
       </div>
 
       <div className='mt-auto d-flex justify-content-between mb-2'>
-        <SubContent title={'WPM'} content={wpm.toString()} />
+        <SubContent title={'CPM'} content={cpm.toString()} />
         <SubContent title={'正確性'} content={`${accuracy.toFixed(0)}%`} />
         <SubContent title={'ミスタイプ'} content={`${effectiveKeyStroke.missedCount}回`} />
         <SubContent title={'字数'} content={`${wordCount}字`} />
@@ -19,7 +19,13 @@ export function ResultView(): JSX.Element {
       completelyCorrectCount: 0,
       missedCount: 0,
     },
-    totalTimeMs: 0
+    totalTimeMs: 0,
+    laps: [],
+    vocabularies: [],
+    scores: {
+      keyStroke: { cpm: 0, accuracy: 0, formula: 'eTyping', score: 0 },
+      idealKeyStroke: { cpm: 0, accuracy: 0, formula: 'eTyping', score: 0 },
+    },
   });
 
   const handleKeyDown = (e: KeyboardEvent) => {
@@ -41,8 +47,16 @@ export function ResultView(): JSX.Element {
     return () => { removeEventListener('keydown', handleKeyDown) }
   });
 
+  const SAVE_WARNING_TEXT = {
+    history: '履歴を保存できませんでした',
+    replay: 'リプレイを保存できませんでした',
+  };
+
   return (
     <div className='w-100 h-100'>
+      {resultStatistics.saveWarnings?.map((saveWarning, i) => (
+        <div key={i} className='alert alert-warning m-2' role='alert'>{`${SAVE_WARNING_TEXT[saveWarning.target]}: ${saveWarning.message}`}</div>
+      ))}
       <div className='p-2 h-50 w-40'>
         <ResultSummaryPane summary={resultStatistics} />
       </div>
@@ -1,5 +1,39 @@
 import React from 'react';
 
+// 無効な行の理由を表示用の文字列にする
+function diagnosticReasonText(reason: ParseDiagnosticReason): string {
+  switch (reason.kind) {
+    case 'missing_colon':
+      return '語彙と読みを区切る「:」がありません';
+    case 'extra_colon':
+      return '「:」が多すぎます';
+    case 'nested_square_parentheses':
+      return '[]が入れ子になっています';
+    case 'unmatched_closing_square_parenthesis':
+      return '対応する「[」がない「]」があります';
+    case 'unclosed_square_parenthesis':
+      return '「[」が閉じられていません';
+    case 'empty_square_parentheses':
+      return '中身が空の[]があります';
+    case 'spell_count_mismatch':
+      return `語彙のまとまりが${reason.view_segment_count}個に対して読みが${reason.spell_count}個です`;
+    case 'invalid_spell':
+      return `読み「${reason.spell}」に使えない文字「${reason.invalid_char}」が含まれています`;
+    case 'invalid_vocabulary':
+      return '語彙を構築できません';
+    case 'unclosed_header':
+      return 'ヘッダーを閉じる「---」がありません';
+    case 'unknown_header_key':
+      return `ヘッダーの項目「${reason.key}」は使えません`;
+    case 'invalid_tag':
+      return '空のタグがあります';
+    case 'normalized_spell':
+      return `読み「${reason.spell}」を「${reason.normalized}」に置き換えました`;
+    case 'tag_like_comment':
+      return `コメント「${reason.tags.map(tag => `#${tag}`).join(' ')}」はタグとして扱われません。タグは「:${reason.tags.join(',')}」のように読みの後ろに書きます`;
+  }
+}
+
 export function SelectDictionaryPane(props: { availableDictionaryList: DictionaryInfo[], usedDictionaryList: [DictionaryOrigin, string][], libraryOperator: (action: LibraryOperatorActionType) => void }): JSX.Element {
   const usedDictionaryOneHot = new Map<string, boolean>(props.usedDictionaryList.map(e => [`${e[0]} ${e[1]}`, true]));
 
@@ -16,6 +50,7 @@ export function SelectDictionaryPane(props: { availableDictionaryList: Dictionar
 
   const DISABLED_DICTIONARY_TOOLTIP_TEXT = '辞書に含まれる語彙がありません';
   const DICTIONARY_CONTAIN_ERROR_TOOLTIP_TEXT_BASE = '以下の行に無効な語彙があります';
+  const DICTIONARY_CONTAIN_NOTICE_TOOLTIP_TEXT_BASE = '以下の行の読みを置き換えて読み込みました';
 
   // 表示用に辞書をソートする
   // TODO ソート順がいろいろあると嬉しい
@@ -40,8 +75,14 @@ export function SelectDictionaryPane(props: { availableDictionaryList: Dictionar
 
     // 辞書に無効な語彙を含むときの警告文の生成
     let containErrorTooltipText = DICTIONARY_CONTAIN_ERROR_TOOLTIP_TEXT_BASE;
-    dictionaryInfo.invalidLineNumberList.forEach(lineNum => {
-      containErrorTooltipText = containErrorTooltipText.concat(`\r\n${lineNum}行目`);
+    dictionaryInfo.diagnostics.forEach(diagnostic => {
+      containErrorTooltipText = containErrorTooltipText.concat(`\r\n${diagnostic.line_number}行目${diagnostic.column_start + 1}文字目: ${diagnosticReasonText(diagnostic.reason)}`);
+    });
+
+    // 読みを置き換えて読み込んだときの通知文の生成
+    let containNoticeTooltipText = DICTIONARY_CONTAIN_NOTICE_TOOLTIP_TEXT_BASE;
+    dictionaryInfo.notices.forEach(notice => {
+      containNoticeTooltipText = containNoticeTooltipText.concat(`\r\n${notice.line_number}行目${notice.column_start + 1}文字目: ${diagnosticReasonText(notice.reason)}`);
     });
 
     const checkbox = (
@@ -51,7 +92,8 @@ export function SelectDictionaryPane(props: { availableDictionaryList: Dictionar
         <span className={`text-start ${!enable && 'text-secondary'}`}>{dictionaryName}</span>
 
         <span className='ms-auto'>
-          {dictionaryInfo.invalidLineNumberList.length != 0 ? <i className='bi bi-exclamation-triangle text-warning' data-bs-toggle='tooltip' data-bs-placement='top' title={containErrorTooltipText} /> : undefined}
+          {dictionaryInfo.notices.length != 0 ? <i className='bi bi-info-circle text-info' data-bs-toggle='tooltip' data-bs-placement='top' title={containNoticeTooltipText} /> : undefined}
+          {dictionaryInfo.diagnostics.length != 0 ? <i className='bi bi-exclamation-triangle text-warning' data-bs-toggle='tooltip' data-bs-placement='top' title={containErrorTooltipText} /> : undefined}
           {!enable ? <i className='bi bi-x-circle text-danger' data-bs-toggle='tooltip' data-bs-placement='top' title={DISABLED_DICTIONARY_TOOLTIP_TEXT} /> : undefined}
         </span>
       </label>
@@ -1,4 +1,5 @@
 import _, { useEffect, useContext, useRef } from 'react';
+import { invoke } from '@tauri-apps/api/tauri';
 import { StartSignal } from './StartSignal';
 import { useCountdownTimer } from './useCountdownTimer';
 import { GameStateContext } from './App';
@@ -19,6 +20,7 @@ export function TransitionToTypingView() {
     const key = e.key;
 
     if (key === 'Escape') {
+      invoke('cancel_game').catch(e => console.log(e));
       gameStateContext.setGameState('ModeSelect');
     }
   }
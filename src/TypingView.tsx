@@ -1,4 +1,5 @@
-import _, { useEffect, useContext, useRef } from 'react';
+import _, { useEffect, useContext, useRef, useState } from 'react';
+import { invoke } from '@tauri-apps/api/tauri';
 import { TimerPane } from './TimerPane';
 import { ViewPane } from './ViewPane';
 import { KeyStrokePane } from './KeyStrokePane';
@@ -8,15 +9,34 @@ import { GameStateContext } from './App';
 import { useMilliSecondTimer } from './useMilliSecondTimer';
 import { useTypingEngine } from './useTypingEngine';
 
+// 打鍵のない間に制限時間が来ていないかをバックエンドに確認する間隔
+const TIME_UP_POLLING_INTERVAL_MS = 100;
+
 export function TypingView() {
-  const [elapsedTime, startTimer, stopTimer, cancelTimer] = useMilliSecondTimer();
-  const [displayInfo, startGame, handleInput] = useTypingEngine(() => { stopTimer(); gameStateContext.setGameState('Finished'); });
+  const [elapsedTime, startTimer, stopTimer, cancelTimer, pauseTimer, resumeTimer] = useMilliSecondTimer();
+  const [displayInfo, startGame, handleInput] = useTypingEngine(() => finishTyping());
+  const [isPaused, setIsPaused] = useState<boolean>(false);
   const isStarted = useRef(false);
 
   const gameStateContext = useContext(GameStateContext);
 
+  const finishTyping = () => {
+    stopTimer();
+    gameStateContext.setGameState('Finished');
+  }
+
+  // 一時停止中は打鍵を受け付けず、経過時間もバックエンドと同じく進めない
+  const togglePause = () => {
+    if (isPaused) {
+      invoke('resume_game').then(() => { setIsPaused(false); resumeTimer(); }).catch(e => console.log(e));
+    } else {
+      invoke('pause_game').then(() => { setIsPaused(true); pauseTimer(); }).catch(e => console.log(e));
+    }
+  }
+
   const cancelTyping = () => {
     // これもuseEffect内でやる必要があるかもしれない
+    invoke('cancel_game').catch(e => console.log(e));
     gameStateContext.setGameState('ModeSelect');
     cancelTimer();
   }
@@ -29,6 +49,10 @@ export function TypingView() {
       return;
     }
 
+    if (isPaused) {
+      return;
+    }
+
     // ShiftとかAltとかの特殊文字を防ぐために長さでバリデーションをかける
     // 本当はもっといいやり方があるはず
     if (key.length == 1 && ' '.charCodeAt(0) <= key.charCodeAt(0) && key.charCodeAt(0) <= '~'.charCodeAt(0)) {
@@ -46,6 +70,25 @@ export function TypingView() {
     }
   }, []);
 
+  // 制限時間の判定はバックエンドで行うので打鍵がなくても定期的に確認する
+  useEffect(() => {
+    if (isPaused) {
+      return;
+    }
+
+    const timerId = setInterval(() => {
+      invoke<boolean>('notify_time_up')
+        .then(finished => {
+          if (finished) {
+            finishTyping();
+          }
+        })
+        .catch(e => console.log(e));
+    }, TIME_UP_POLLING_INTERVAL_MS);
+
+    return () => { clearInterval(timerId) };
+  }, [isPaused]);
+
   useEffect(() => {
     addEventListener('keydown', handleKeyDown);
 
@@ -72,6 +115,9 @@ export function TypingView() {
           </div>
         </div>
         <div className='d-flex align-items-center'>
+          <button className='btn btn-sm btn-outline-secondary me-2' onClick={e => { e.currentTarget.blur(); togglePause(); }}>
+            <i className={`bi ${isPaused ? 'bi-play-fill' : 'bi-pause-fill'}`} />
+          </button>
           <TimerPane elapsedTimeMilli={elapsedTime} />
         </div>
       </div>
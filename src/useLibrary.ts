@@ -1,5 +1,6 @@
 import { useEffect, useReducer } from 'react';
 import { invoke } from '@tauri-apps/api';
+import { listen } from '@tauri-apps/api/event';
 
 export function useLibrary(): [Library, (action: LibraryOperatorActionType) => void] {
 
@@ -7,8 +8,12 @@ export function useLibrary(): [Library, (action: LibraryOperatorActionType) => v
   type DictionaryInfoFromCore = {
     dictionary_type: DictionaryType,
     origin: DictionaryOrigin,
-    invalid_line_numbers: number[],
+    diagnostics: ParseDiagnostic[],
+    notices: ParseDiagnostic[],
     name: string,
+    category: string,
+    metadata: DictionaryMetadata,
+    tags: string[],
     valid_vocabulary_count: number,
   };
 
@@ -21,6 +26,8 @@ export function useLibrary(): [Library, (action: LibraryOperatorActionType) => v
     dictionaryType: DictionaryType,
     usedDictionaries: [DictionaryOrigin, String][],
     keyStrokeCountThreshold?: number,
+    tagFilter?: TagFilter,
+    scoringFormula: ScoringFormula,
   }
 
   type LibraryReducerActionType =
@@ -28,7 +35,8 @@ export function useLibrary(): [Library, (action: LibraryOperatorActionType) => v
     | { type: 'disuse', dictionaryName: string, dictionaryOrigin: DictionaryOrigin }
     | { type: 'load', availableDictionaryList: CategorizedDictionaryInfoList }
     | { type: 'type', dictionaryType: DictionaryType }
-    | { type: 'keyStrokeCountThreshold', keyStrokeCountThreshold: number };
+    | { type: 'keyStrokeCountThreshold', keyStrokeCountThreshold: number }
+    | { type: 'scoringFormula', scoringFormula: ScoringFormula };
 
   type CategorizedDictionaryInfoList = {
     word: DictionaryInfo[],
@@ -44,6 +52,7 @@ export function useLibrary(): [Library, (action: LibraryOperatorActionType) => v
     },
     usedDictionaryType: DictionaryType,
     keyStrokeCountThreshold: number,
+    scoringFormula: ScoringFormula,
   }
 
   const existInAvailableDictionary = (availableDictionaryList: CategorizedDictionaryInfoList, dictionaryName: string, vocabularyType: DictionaryType, dictionaryOrigin: DictionaryOrigin) => {
@@ -85,6 +94,7 @@ export function useLibrary(): [Library, (action: LibraryOperatorActionType) => v
           usedDictionaries: addedUsedDictionaries,
           usedDictionaryType: state.usedDictionaryType,
           keyStrokeCountThreshold: state.keyStrokeCountThreshold,
+          scoringFormula: state.scoringFormula,
         };
 
       // 現在有効になっている辞書タイプで利用可能な辞書を不使用とする
@@ -107,6 +117,7 @@ export function useLibrary(): [Library, (action: LibraryOperatorActionType) => v
           usedDictionaries: deletedUsedDictionaryFileNameList,
           usedDictionaryType: state.usedDictionaryType,
           keyStrokeCountThreshold: state.keyStrokeCountThreshold,
+          scoringFormula: state.scoringFormula,
         };
 
       case 'load':
@@ -122,6 +133,7 @@ export function useLibrary(): [Library, (action: LibraryOperatorActionType) => v
           },
           usedDictionaryType: state.usedDictionaryType,
           keyStrokeCountThreshold: state.keyStrokeCountThreshold,
+          scoringFormula: state.scoringFormula,
         };
 
       // 使用する辞書タイプを変更する
@@ -131,6 +143,7 @@ export function useLibrary(): [Library, (action: LibraryOperatorActionType) => v
           usedDictionaries: state.usedDictionaries,
           usedDictionaryType: action.dictionaryType,
           keyStrokeCountThreshold: state.keyStrokeCountThreshold,
+          scoringFormula: state.scoringFormula,
         };
 
       // 
@@ -140,43 +153,64 @@ export function useLibrary(): [Library, (action: LibraryOperatorActionType) => v
           usedDictionaries: state.usedDictionaries,
           usedDictionaryType: state.usedDictionaryType,
           keyStrokeCountThreshold: action.keyStrokeCountThreshold,
+          scoringFormula: state.scoringFormula,
+        };
+
+      // 結果の主なスコアの算出方法を変更する
+      case 'scoringFormula':
+        return {
+          availableDictionaries: state.availableDictionaries,
+          usedDictionaries: state.usedDictionaries,
+          usedDictionaryType: state.usedDictionaryType,
+          keyStrokeCountThreshold: state.keyStrokeCountThreshold,
+          scoringFormula: action.scoringFormula,
         };
     }
   }
 
   const loadAvailableDictionaryList = () => {
-    invoke<CategorizedDictionaryInfosFromCore>('get_dictionary_infos').then((categorizedDictionaryInfos: CategorizedDictionaryInfosFromCore) => {
-      let availableDictionaryList: CategorizedDictionaryInfoList = {
-        word: [],
-        sentence: [],
-      };
-
-      categorizedDictionaryInfos.word.forEach(wordDictionary => {
-        availableDictionaryList.word.push({
-          name: wordDictionary.name,
-          origin: wordDictionary.origin,
-          type: 'word',
-          validVocabularyCount: wordDictionary.valid_vocabulary_count,
-          invalidLineNumberList: wordDictionary.invalid_line_numbers,
-        });
-      });
+    invoke<CategorizedDictionaryInfosFromCore>('get_dictionary_infos').then(applyAvailableDictionaryList);
+  };
 
-      categorizedDictionaryInfos.sentence.forEach(sentenceDictionary => {
-        availableDictionaryList.sentence.push({
-          name: sentenceDictionary.name,
-          type: 'sentence',
-          origin: sentenceDictionary.origin,
-          validVocabularyCount: sentenceDictionary.valid_vocabulary_count,
-          invalidLineNumberList: sentenceDictionary.invalid_line_numbers,
-        });
+  const applyAvailableDictionaryList = (categorizedDictionaryInfos: CategorizedDictionaryInfosFromCore) => {
+    let availableDictionaryList: CategorizedDictionaryInfoList = {
+      word: [],
+      sentence: [],
+    };
+
+    categorizedDictionaryInfos.word.forEach(wordDictionary => {
+      availableDictionaryList.word.push({
+        name: wordDictionary.name,
+        category: wordDictionary.category,
+        origin: wordDictionary.origin,
+        type: 'word',
+        metadata: wordDictionary.metadata,
+        tags: wordDictionary.tags,
+        validVocabularyCount: wordDictionary.valid_vocabulary_count,
+        diagnostics: wordDictionary.diagnostics,
+        notices: wordDictionary.notices,
       });
+    });
 
-      dispatchLibrary({ type: 'load', availableDictionaryList: availableDictionaryList });
+    categorizedDictionaryInfos.sentence.forEach(sentenceDictionary => {
+      availableDictionaryList.sentence.push({
+        name: sentenceDictionary.name,
+        category: sentenceDictionary.category,
+        type: 'sentence',
+        origin: sentenceDictionary.origin,
+        metadata: sentenceDictionary.metadata,
+        tags: sentenceDictionary.tags,
+        validVocabularyCount: sentenceDictionary.valid_vocabulary_count,
+        diagnostics: sentenceDictionary.diagnostics,
+        notices: sentenceDictionary.notices,
+      });
     });
+
+    dispatchLibrary({ type: 'load', availableDictionaryList: availableDictionaryList });
   };
 
   const confirmQuery = () => {
-    let request: QueryRequestToCore = { dictionaryType: effectiveVocabularyType, usedDictionaries: effectiveUsedDictionaries };
+    let request: QueryRequestToCore = { dictionaryType: effectiveVocabularyType, usedDictionaries: effectiveUsedDictionaries, scoringFormula: innerLibrary.scoringFormula };
 
     if (effectiveVocabularyType == 'word') {
       request.keyStrokeCountThreshold = innerLibrary.keyStrokeCountThreshold;
@@ -203,6 +237,9 @@ export function useLibrary(): [Library, (action: LibraryOperatorActionType) => v
       case 'keyStrokeCountThreshold':
         dispatchLibrary({ type: 'keyStrokeCountThreshold', keyStrokeCountThreshold: action.keyStrokeCountThreshold });
         break;
+      case 'scoringFormula':
+        dispatchLibrary({ type: 'scoringFormula', scoringFormula: action.scoringFormula });
+        break;
       case 'confirmQuery':
         confirmQuery();
         break;
@@ -214,11 +251,21 @@ export function useLibrary(): [Library, (action: LibraryOperatorActionType) => v
     loadAvailableDictionaryList();
   }, []);
 
+  // 外部のエディタで辞書が編集された場合などにバックエンドから通知される
+  useEffect(() => {
+    const unlisten = listen<CategorizedDictionaryInfosFromCore>('dictionaries_changed', event => applyAvailableDictionaryList(event.payload));
+
+    return () => {
+      unlisten.then(f => f());
+    };
+  }, []);
+
   const [innerLibrary, dispatchLibrary] = useReducer(libraryReducer, {
     availableDictionaries: { word: [], sentence: [] },
     usedDictionaries: { word: [], sentence: [] },
     usedDictionaryType: 'word',
     keyStrokeCountThreshold: 150,
+    scoringFormula: 'eTyping',
   });
 
   // 返却する型は現在有効な辞書タイプの情報のみを持つ
@@ -231,6 +278,7 @@ export function useLibrary(): [Library, (action: LibraryOperatorActionType) => v
     availableDictionaries: effectiveAvailableDictionaries,
     usedDictionaryType: effectiveVocabularyType,
     keyStrokeCountThreshold: innerLibrary.keyStrokeCountThreshold,
+    scoringFormula: innerLibrary.scoringFormula,
   };
 
   return [library, operator];
@@ -2,10 +2,12 @@ import { useState, useRef, useEffect } from 'react';
 
 const PRECISION_MS = 10;
 
-export function useMilliSecondTimer(): [number, () => void, () => void, () => void] {
-  type TimerState = 'Ready' | 'Started' | 'Stopped';
+export function useMilliSecondTimer(): [number, () => void, () => void, () => void, () => void, () => void] {
+  type TimerState = 'Ready' | 'Started' | 'Paused' | 'Stopped';
 
   const startDate = useRef<Date>(new Date());
+  // 一時停止した時刻で、再開時にこの間の時間を経過時間から除く
+  const pausedDate = useRef<Date | null>(null);
 
   const [elapsedTime, setElapsedTime] = useState<number>(0);
   const [timerState, setTimerState] = useState<TimerState>('Ready');
@@ -45,5 +47,18 @@ export function useMilliSecondTimer(): [number, () => void, () => void, () => vo
     setTimerState('Ready');
   }
 
-  return [elapsedTime, startTimer, stopTimer, cancelTimer];
+  function pauseTimer(): void {
+    pausedDate.current = new Date();
+    setTimerState('Paused');
+  }
+
+  function resumeTimer(): void {
+    if (pausedDate.current !== null) {
+      startDate.current = new Date(startDate.current.getTime() + new Date().getTime() - pausedDate.current.getTime());
+      pausedDate.current = null;
+    }
+    setTimerState('Started');
+  }
+
+  return [elapsedTime, startTimer, stopTimer, cancelTimer, pauseTimer, resumeTimer];
 }